@@ -0,0 +1,74 @@
+//! Per-tick cost of every broad phase backend inside `GameState::tick`.
+//!
+//! Spawns 100, 1 000 and 10 000 balls with `Ball::random_ball` in the classic 640x480 arena
+//! and times whole ticks, so the broad phase update, the candidate search and the narrow
+//! phase on the candidates are all included. Before timing, the incremental quadtree is
+//! checked against a rebuilt one. That every backend finds the collisions a brute-force scan
+//! finds is checked on the same scene by `cargo test` (tests/broadphase.rs). Run with
+//! `cargo bench --bench broadphase`.
+
+use circles::broadphase::BroadPhaseKind;
+use circles::levels::LevelPack;
+use circles::logic::GameState;
+use circles::quadtree::QuadTree;
+use std::time::Instant;
+
+const COUNTS: [usize; 3] = [100, 1_000, 10_000];
+
+// keeps the brute-force runs at 10k balls bearable
+const PAIR_CHECKS_PER_BACKEND: usize = 100_000_000;
+
+fn game(count: usize, kind: BroadPhaseKind) -> GameState {
+    let json = format!(
+        r#"{{ "name": "bench", "arena": {{ "width": 640, "height": 480 }},
+              "levels": [{{ "shots": 1, "balls": {}, "capture": 1 }}] }}"#,
+        count
+    );
+    let pack = LevelPack::from_json(&json).expect("bench level pack should be valid");
+    let mut game = GameState::from_level_pack(&pack, 1);
+    game.use_broad_phase(kind);
+    game
+}
+
+// the incremental quadtree against a rebuilt one each tick, in the release build the game ships
+fn check_incremental(count: usize) {
+    let mut game = game(count, BroadPhaseKind::QuadTree);
+    let mut tree = QuadTree::new(game.rect);
+    tree.check_incremental = true;
+    game.broad_phase = Box::new(tree);
+    for _ in 0..3 {
+        game.tick();
+    }
+}
+
+fn measure(count: usize, kind: BroadPhaseKind) {
+    let mut game = game(count, kind);
+    // warm up so reused storage has grown to size
+    game.tick();
+
+    let frames = (PAIR_CHECKS_PER_BACKEND / (count * count)).clamp(1, 200);
+    let start = Instant::now();
+    for _ in 0..frames {
+        game.tick();
+    }
+    let elapsed = start.elapsed();
+
+    println!(
+        "{:>6} balls  {:<16} {:>10.1} us/tick {:>10} candidates",
+        count,
+        kind.to_string(),
+        elapsed.as_secs_f64() * 1e6 / frames as f64,
+        game.broad_phase.candidates().len()
+    );
+}
+
+fn main() {
+    for count in COUNTS {
+        for kind in BroadPhaseKind::ALL {
+            if kind == BroadPhaseKind::QuadTree {
+                check_incremental(count);
+            }
+            measure(count, kind);
+        }
+    }
+}
@@ -0,0 +1,159 @@
+//! Per-frame cost of rebuilding the quadtree, counting heap allocations.
+//!
+//! Compares the arena quadtree reused between frames or updated incrementally against
+//! building a fresh one every frame, and against the old boxed tree that kept a copy of
+//! every ball in every node. Run with `cargo bench --bench quadtree`.
+
+use circles::ball::{Ball, BallType};
+use circles::geometry::{Cells, Rect};
+use circles::quadtree::QuadTree;
+use circles::random::Random;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+const FRAMES: usize = 200;
+
+// the tree before it moved into an arena: boxed children and a copy of every ball per node
+struct BoxedNode {
+    rect: Rect,
+    balls: HashMap<usize, Ball>,
+    children: [Option<Box<BoxedNode>>; 4],
+}
+
+impl BoxedNode {
+    fn new(rect: Rect) -> Box<Self> {
+        Box::new(Self {
+            rect,
+            balls: HashMap::new(),
+            children: [None, None, None, None],
+        })
+    }
+
+    fn insert_ball(&mut self, ball: &Ball) {
+        self.balls.insert(ball.id, *ball);
+
+        let (slot, cell) = match self.rect.where_is_point_relative_to_center(ball.pos) {
+            Cells::TopLeft => (0, self.rect.top_left()),
+            Cells::TopRight => (1, self.rect.top_right()),
+            Cells::BottomLeft => (2, self.rect.bottom_left()),
+            Cells::BottomRight => (3, self.rect.bottom_right()),
+        };
+        if !ball.bounding_rect_swept().fits(cell) {
+            return;
+        }
+        self.children[slot]
+            .get_or_insert_with(|| BoxedNode::new(cell))
+            .insert_ball(ball);
+    }
+}
+
+fn spawn(count: usize, rect: Rect) -> HashMap<usize, Ball> {
+    let mut rng = Random::new(1);
+    (1..=count)
+        .map(|id| {
+            let ball_type = BallType::random_ball_type(&mut rng);
+            let ball = Ball::random_ball(&mut rng, id, rect.w as usize, rect.h as usize, ball_type);
+            (id, ball)
+        })
+        .collect()
+}
+
+fn move_balls(objects: &mut HashMap<usize, Ball>, rect: Rect) {
+    for ball in objects.values_mut() {
+        ball.tick(rect);
+        ball.apply_tick_changes();
+    }
+}
+
+fn measure(name: &str, count: usize, rect: Rect, mut frame: impl FnMut(&HashMap<usize, Ball>)) {
+    let mut objects = spawn(count, rect);
+    // warm up so reused storage has grown to size
+    frame(&objects);
+
+    let mut allocations = 0;
+    let start = Instant::now();
+    for _ in 0..FRAMES {
+        move_balls(&mut objects, rect);
+        let before = ALLOCATIONS.load(Ordering::Relaxed);
+        frame(&objects);
+        allocations += ALLOCATIONS.load(Ordering::Relaxed) - before;
+    }
+    let elapsed = start.elapsed();
+
+    println!(
+        "{:>6} balls  {:<16} {:>10.1} allocations/frame {:>10.1} us/frame",
+        count,
+        name,
+        allocations as f64 / FRAMES as f64,
+        elapsed.as_secs_f64() * 1e6 / FRAMES as f64
+    );
+}
+
+fn main() {
+    let rect = Rect {
+        x: 0.0,
+        y: 0.0,
+        w: 640.0,
+        h: 480.0,
+    };
+
+    for count in [1_000, 2_000, 5_000] {
+        measure("boxed (old)", count, rect, |objects| {
+            let mut tree = BoxedNode::new(rect);
+            for ball in objects.clone().values() {
+                tree.insert_ball(ball);
+            }
+            assert_eq!(tree.balls.len(), objects.len());
+        });
+
+        measure("arena, fresh", count, rect, |objects| {
+            let mut tree = QuadTree::new(rect);
+            for ball in objects.values() {
+                tree.insert_ball(ball);
+            }
+            assert_eq!(tree.root().balls.len(), objects.len());
+        });
+
+        let mut tree = QuadTree::new(rect);
+        measure("arena, reused", count, rect, |objects| {
+            tree.clear(rect);
+            for ball in objects.values() {
+                tree.insert_ball(ball);
+            }
+            assert_eq!(tree.root().balls.len(), objects.len());
+        });
+
+        let mut tree = QuadTree::new(rect);
+        measure("arena, moved", count, rect, |objects| {
+            for ball in objects.values() {
+                tree.move_ball(ball);
+            }
+            assert_eq!(tree.root().balls.len(), objects.len());
+        });
+    }
+}
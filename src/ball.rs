@@ -1,13 +1,13 @@
 use crate::ball::BallState::{Expanding, Normal, Shrinking, Vanish};
-use crate::constants;
 use crate::constants::{
-    BIG, COLOSSAL, EXPLODES, FAST, FULL, GROWS, LARGE, SHRINK, SLOW, SMALL, TINY,
+    BIG, COLOSSAL, EXPLODES, FAST, FULL, GROWS, LARGE, SHRINK, SLOW, SMALL, TICKS_PER_SECOND,
+    TINY,
 };
 use crate::geometry::{Point, Rect};
-use crate::random::{random_range, random_range_descending};
-use js_sys::Math::min;
+use crate::random::Random;
+use std::fmt;
+use std::hash::{Hash, Hasher};
 use std::ops::Div;
-use web_sys::console;
 
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Color {
@@ -16,9 +16,9 @@ pub struct Color {
     pub b: u8,
 }
 
-impl Color {
-    pub fn to_string(self) -> String {
-        format!("rgb({},{},{})", self.r, self.g, self.b)
+impl fmt::Display for Color {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "rgb({},{},{})", self.r, self.g, self.b)
     }
 }
 
@@ -98,7 +98,7 @@ pub enum BallState {
     Vanish,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct BallType {
     color: Color,
     radius: f64,
@@ -108,32 +108,44 @@ pub struct BallType {
     name: &'static str,
 }
 
+// ordered from most to least common, see `random_range_descending`
+const RANDOM_BALL_TYPES: [BallType; 17] = [
+    WHITE_BALL,
+    ORANGE_BALL,
+    BROWN_BALL,
+    NAVY_BALL,
+    PURPLE_BALL,
+    GRAY_BALL,
+    YELLOW_BALL,
+    RED_BALL,
+    CYAN_BALL,
+    MAGENTA_BALL,
+    TEAL_BALL,
+    BLUE_BALL,
+    GREEN_BALL,
+    OLIVE_BALL,
+    MAROON_BALL,
+    FORREST_BALL,
+    SILVER_BALL,
+    //            BLACK_BALL,
+];
+
 impl BallType {
-    pub fn random_ball_type() -> BallType {
-        let ball_types = vec![
-            WHITE_BALL,
-            ORANGE_BALL,
-            BROWN_BALL,
-            NAVY_BALL,
-            PURPLE_BALL,
-            GRAY_BALL,
-            YELLOW_BALL,
-            RED_BALL,
-            CYAN_BALL,
-            MAGENTA_BALL,
-            TEAL_BALL,
-            BLUE_BALL,
-            GREEN_BALL,
-            OLIVE_BALL,
-            MAROON_BALL,
-            FORREST_BALL,
-            SILVER_BALL,
-            //            BLACK_BALL,
-        ];
-
-        let elm = ball_types[random_range_descending(0, ball_types.len())];
-
-        return elm;
+    pub fn random_ball_type(rng: &mut Random) -> BallType {
+        RANDOM_BALL_TYPES[rng.random_range_descending(0, RANDOM_BALL_TYPES.len())]
+    }
+
+    // looks up ball types by their constant's name, e.g. "RED_BALL"; ACTIVE_BALL can't be spawned
+    pub fn from_name(name: &str) -> Option<BallType> {
+        RANDOM_BALL_TYPES
+            .iter()
+            .chain([BLACK_BALL].iter())
+            .find(|ball_type| ball_type.name == name)
+            .copied()
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
     }
 }
 
@@ -329,12 +341,16 @@ pub struct Ball {
     pub ball_type: &'static str,
     pub pos: Point,
     pub radius: f64,
-    // velocity is a 2d vector
+    // velocity is a 2d vector, in units per second
     pub velocity: Point,
     pub color: Color,
     pub ball_state: BallState,
+    // units per second
     pub radius_growth: f64,
     pub max_radius: f64,
+    // where the ball was a tick ago, for drawing it between ticks
+    pub previous_position: Point,
+    pub previous_radius: f64,
     //
     pub next_position: Point,
     pub next_radius: f64,
@@ -361,6 +377,8 @@ impl Ball {
             color: ball_type.color,
             radius_growth: ball_type.growth_speed,
             max_radius: ball_type.growth_size,
+            previous_position: pos,
+            previous_radius: ball_type.radius,
 
             next_position: pos,
             next_velocity: velocity,
@@ -370,9 +388,21 @@ impl Ball {
         }
     }
 
-    pub fn random_ball(id: usize, width: usize, height: usize, ball_type: BallType) -> Self {
-        let pos = Point::random_point(width, height);
-        let vel = Point::random_velocity(ball_type.velocity * 0.8, ball_type.velocity * 1.2);
+    pub fn random_ball(
+        rng: &mut Random,
+        id: usize,
+        width: usize,
+        height: usize,
+        ball_type: BallType,
+    ) -> Self {
+        let pos = Point::random_point(rng, width, height);
+        // picked per tick, `random_velocity` rounds down to whole units
+        let per_tick = ball_type.velocity / TICKS_PER_SECOND;
+        let vel = Point::random_velocity(rng, per_tick * 0.8, per_tick * 1.2);
+        let vel = Point {
+            x: vel.x * TICKS_PER_SECOND,
+            y: vel.y * TICKS_PER_SECOND,
+        };
 
         Self {
             id,
@@ -383,6 +413,8 @@ impl Ball {
             color: ball_type.color,
             radius_growth: ball_type.growth_speed,
             max_radius: ball_type.growth_size,
+            previous_position: pos,
+            previous_radius: ball_type.radius,
             ball_state: Normal,
 
             next_position: pos,
@@ -393,27 +425,36 @@ impl Ball {
         }
     }
 
-    fn check_wall_collisions(&mut self) {
-        let next_x = self.pos.x + self.velocity.x;
-        let next_y = self.pos.y + self.velocity.y;
+    // distance covered in one tick
+    pub fn step(&self) -> Point {
+        Point {
+            x: self.velocity.x / TICKS_PER_SECOND,
+            y: self.velocity.y / TICKS_PER_SECOND,
+        }
+    }
+
+    fn check_wall_collisions(&mut self, arena: Rect) {
+        let step = self.step();
+        let next_x = self.pos.x + step.x;
+        let next_y = self.pos.y + step.y;
 
-        if next_x + self.radius > constants::WIDTH as f64 {
+        if next_x + self.radius > arena.x + arena.w {
             self.next_velocity.x = -self.velocity.x;
         }
-        if next_x - self.radius < 0.0 {
+        if next_x - self.radius < arena.x {
             self.next_velocity.x = -self.velocity.x;
         }
-        if next_y + self.radius > constants::HEIGHT as f64 {
+        if next_y + self.radius > arena.y + arena.h {
             self.next_velocity.y = -self.velocity.y;
         }
 
-        if next_y - self.radius < 0.0 {
+        if next_y - self.radius < arena.y {
             self.next_velocity.y = -self.velocity.y;
         }
 
         self.next_position = Point {
-            x: self.pos.x + self.velocity.x,
-            y: self.pos.y + self.velocity.y,
+            x: self.pos.x + step.x,
+            y: self.pos.y + step.y,
         };
     }
 
@@ -421,14 +462,17 @@ impl Ball {
         match self.ball_state {
             Expanding => {
                 self.next_velocity = Point { x: 0.0, y: 0.0 };
-                self.next_radius = min(self.radius + self.radius_growth, self.max_radius);
+                self.next_radius = f64::min(
+                    self.radius + self.radius_growth / TICKS_PER_SECOND,
+                    self.max_radius,
+                );
                 if self.next_radius == self.max_radius {
                     self.next_ball_state = Shrinking;
                 }
             }
             Shrinking => {
                 self.next_velocity = Point { x: 0.0, y: 0.0 };
-                self.next_radius += SHRINK / self.radius;
+                self.next_radius += SHRINK / TICKS_PER_SECOND / self.radius;
                 if self.next_radius < 5.0 {
                     self.next_ball_state = Vanish;
                 }
@@ -440,18 +484,35 @@ impl Ball {
     }
 
     pub fn apply_tick_changes(&mut self) {
+        self.previous_position = self.pos;
+        self.previous_radius = self.radius;
         self.pos = self.next_position;
         self.velocity = self.next_velocity;
         self.radius = self.next_radius;
         self.ball_state = self.next_ball_state;
     }
 
-    pub fn tick(&mut self) {
+    // `arena` is the box the walls are on
+    pub fn tick(&mut self, arena: Rect) {
         // walls
-        self.check_wall_collisions();
+        self.check_wall_collisions(arena);
         self.handle_ball_states();
     }
 
+    // the ball `alpha` of the way from its previous tick to the current one
+    pub fn interpolated(&self, alpha: f64) -> Ball {
+        if alpha >= 1.0 {
+            return *self;
+        }
+        let mut ball = *self;
+        ball.pos = Point {
+            x: self.previous_position.x + (self.pos.x - self.previous_position.x) * alpha,
+            y: self.previous_position.y + (self.pos.y - self.previous_position.y) * alpha,
+        };
+        ball.radius = self.previous_radius + (self.radius - self.previous_radius) * alpha;
+        ball
+    }
+
     pub fn change_ball_state(&mut self, state: BallState) {
         self.ball_state = state;
     }
@@ -460,10 +521,6 @@ impl Ball {
         self.is_captured = true;
     }
 
-    pub fn show_state(&self) {
-        console::log_1(&format!("bala na poziciji: {} {}", self.pos.x, self.pos.y).into());
-    }
-
     pub fn bounding_rect_next(&self) -> Rect {
         Rect {
             x: self.next_position.x - self.next_radius,
@@ -473,17 +530,35 @@ impl Ball {
         }
     }
 
+    // box around everything the ball covers during the tick, used for broad phase
+    pub fn bounding_rect_swept(&self) -> Rect {
+        let current = self.bounding_rect_current();
+        let next = self.bounding_rect_next();
+        let x = f64::min(current.x, next.x);
+        let y = f64::min(current.y, next.y);
+
+        Rect {
+            x,
+            y,
+            w: f64::max(current.x + current.w, next.x + next.w) - x,
+            h: f64::max(current.y + current.h, next.y + next.h) - y,
+        }
+    }
+
+    // Box at the current position. The swept narrow phase starts from `radius` and
+    // `BallPair::is_collision` uses `next_radius` here, so it holds the larger of both.
     pub fn bounding_rect_current(&self) -> Rect {
+        let radius = f64::max(self.radius, self.next_radius);
         Rect {
-            x: self.pos.x - self.next_radius,
-            y: self.pos.y - self.next_radius,
-            w: 2.0 * self.next_radius,
-            h: 2.0 * self.next_radius,
+            x: self.pos.x - radius,
+            y: self.pos.y - radius,
+            w: 2.0 * radius,
+            h: 2.0 * radius,
         }
     }
 }
 
-#[derive(Debug, Clone, Copy, Hash)]
+#[derive(Debug, Clone, Copy)]
 pub struct BallPairIds {
     pub first: usize,
     pub second: usize,
@@ -508,6 +583,14 @@ impl PartialEq for BallPairIds {
 
 impl Eq for BallPairIds {}
 
+// pairs are unordered, so (a, b) and (b, a) have to hash the same
+impl Hash for BallPairIds {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        usize::min(self.first, self.second).hash(state);
+        usize::max(self.first, self.second).hash(state);
+    }
+}
+
 impl IntoIterator for BallPairIds {
     type Item = usize;
     type IntoIter = std::array::IntoIter<usize, 2>;
@@ -537,9 +620,11 @@ impl PartialEq for BallPair {
 impl Eq for BallPair {}
 
 impl BallPair {
+    // exact circle test at the current positions with the next radii, inside the boxes of
+    // `bounding_rect_current`
     pub fn is_collision(&self) -> bool {
         balls_distance_squared(self.first, self.second)
-            <= (f64::powi(self.first.radius, 2) + f64::powi(self.second.radius, 2))
+            <= f64::powi(self.first.next_radius + self.second.next_radius, 2)
     }
 
     pub fn is_collision_bb(&self) -> bool {
@@ -551,11 +636,11 @@ impl BallPair {
 }
 
 fn circles_distance_squared(x1: f64, y1: f64, x2: f64, y2: f64) -> f64 {
-    return (x2 - x1) * (x2 - x1) + (y2 - y1) * (y2 - y1);
+    (x2 - x1) * (x2 - x1) + (y2 - y1) * (y2 - y1)
 }
 
 pub fn balls_distance_squared(ball1: Ball, ball2: Ball) -> f64 {
-    return circles_distance_squared(ball1.pos.x, ball1.pos.y, ball2.pos.x, ball2.pos.y);
+    circles_distance_squared(ball1.pos.x, ball1.pos.y, ball2.pos.x, ball2.pos.y)
 }
 
 pub fn is_point_in_rect(x: f64, y: f64, w: f64, h: f64, point_x: f64, point_y: f64) -> bool {
@@ -661,105 +746,105 @@ pub fn resolve_collision_active(ball1: &mut Ball, ball2: &mut Ball) {
     }
 }
 
+// Elastic collision response on the velocities the balls will move with next,
+// conserving momentum and kinetic energy. Masses are proportional to radius squared.
+// Pairs that are already moving apart are left alone so overlapping balls don't stick.
 pub fn resolve_collision(mut pair: BallPair) -> BallPair {
-    let x1b = pair.first.pos.x;
-    let y1b = pair.first.pos.y;
-
-    let x2b = pair.second.pos.x;
-    let y2b = pair.second.pos.y;
+    let dx = pair.second.pos.x - pair.first.pos.x;
+    let dy = pair.second.pos.y - pair.first.pos.y;
+    let dist = f64::sqrt(dx * dx + dy * dy);
+    if dist == 0.0 {
+        return pair;
+    }
+    let (nx, ny) = (dx / dist, dy / dist);
 
-    let vx1b = pair.first.velocity.x;
-    let vy1b = pair.first.velocity.y;
+    let v1 = pair.first.next_velocity;
+    let v2 = pair.second.next_velocity;
 
-    let vx2b = pair.second.velocity.x;
-    let vy2b = pair.second.velocity.y;
+    // closing speed along the line between centers
+    let approach = (v1.x - v2.x) * nx + (v1.y - v2.y) * ny;
+    if approach <= 0.0 {
+        return pair;
+    }
 
     let m1 = f64::powi(pair.first.radius, 2);
     let m2 = f64::powi(pair.second.radius, 2);
+    let impulse = 2.0 * approach / (m1 + m2);
 
-    let energy_before = 0.5
-        * (m1 * (f64::powi(vx1b, 2) + f64::powi(vy1b, 2))
-            + m2 * (f64::powi(vx2b, 2) + f64::powi(vy2b, 2)));
+    pair.first.next_velocity = Point {
+        x: v1.x - impulse * m2 * nx,
+        y: v1.y - impulse * m2 * ny,
+    };
+    pair.second.next_velocity = Point {
+        x: v2.x + impulse * m1 * nx,
+        y: v2.y + impulse * m1 * ny,
+    };
 
-    return pair;
+    pair
 }
 
+// Time of impact within the current tick, as a fraction in [0, 1], of two balls
+// moving from `pos` to `next_position` while their radius changes from `radius`
+// to `next_radius`. Balls that already overlap at the start collide at 0.
 pub fn calc_moment_of_collision(ball1: &Ball, ball2: &Ball) -> Option<f64> {
-    let first_x = ball1.pos.x;
-    let first_next_x = ball1.next_position.x;
-    let first_y = ball1.pos.y;
-    let first_next_y = ball1.next_position.y;
-    let first_r = ball1.radius;
-
-    let second_x = ball2.pos.x;
-    let second_next_x = ball2.next_position.x;
-    let second_y = ball2.pos.y;
-    let second_next_y = ball2.next_position.y;
-    let second_r = ball2.radius;
-
-    console::log_1(&format!("calculatating collision {} {} ", ball1.id, ball2.id).into());
-
-    return calc_moment_of_collision_helper(
-        first_x,
-        first_next_x,
-        first_y,
-        first_next_y,
-        first_r,
-        second_x,
-        second_next_x,
-        second_y,
-        second_next_y,
-        second_r,
-    );
+    calc_moment_of_collision_helper(
+        Point {
+            x: ball1.pos.x - ball2.pos.x,
+            y: ball1.pos.y - ball2.pos.y,
+        },
+        Point {
+            x: ball1.next_position.x - ball2.next_position.x,
+            y: ball1.next_position.y - ball2.next_position.y,
+        },
+        ball1.radius + ball2.radius,
+        ball1.next_radius + ball2.next_radius,
+    )
 }
 
+// Solves |offset(t)| = radius(t) for the smallest t in [0, 1], where the offset
+// between the centers and the sum of radii both change linearly over the tick:
+//
+//   (D·D - S²) t² + 2 (d·D - r S) t + (d·d - r²) = 0
+//
+// with d the start offset, D the change of offset, r the start radius and S the change of radius.
 pub fn calc_moment_of_collision_helper(
-    first_x: f64,
-    first_next_x: f64,
-    first_y: f64,
-    first_next_y: f64,
-    first_r: f64,
-    second_x: f64,
-    second_next_x: f64,
-    second_y: f64,
-    second_next_y: f64,
-    second_r: f64,
+    start_offset: Point,
+    end_offset: Point,
+    start_radius: f64,
+    end_radius: f64,
 ) -> Option<f64> {
-    let a_dif = first_x - second_x;
-    let b_dif = first_next_x - second_next_x;
-    let c_dif = first_y - second_y;
-    let d_dif = first_next_y - second_next_y;
-
-    let e = f64::powi(a_dif, 2) + f64::powi(c_dif, 2);
-    let f = f64::powi(b_dif, 2) + f64::powi(d_dif, 2);
-    let g = a_dif * b_dif + c_dif * d_dif;
-
-    let r_sq = f64::powi(first_r, 2) + f64::powi(second_r, 2);
-
-    let a = e + f + g;
-    let b = g - 2.0 * e;
-    let c = e - r_sq;
-    let d = f64::powi(b_dif, 2) - 4.0 * a * c;
-    console::log_1(&format!("calculated discriminant {} ", d).into());
-    if d < 0.0 {
-        //something went horribly wrong
-        return None;
+    let dx = end_offset.x - start_offset.x;
+    let dy = end_offset.y - start_offset.y;
+    let dr = end_radius - start_radius;
+
+    let a = dx * dx + dy * dy - dr * dr;
+    let b = 2.0 * (start_offset.x * dx + start_offset.y * dy - start_radius * dr);
+    let c = f64::powi(start_offset.x, 2) + f64::powi(start_offset.y, 2)
+        - f64::powi(start_radius, 2);
+
+    if c <= 0.0 {
+        return Some(0.0);
     }
 
-    let rez1 = (-b + f64::sqrt(d)).div(a);
-    let rez2 = (-b - f64::sqrt(d)).div(a);
-    console::log_1(&format!("-----------").into());
-    console::log_1(&format!("calculated rez1  {} ", rez1).into());
-    console::log_1(&format!("calculated rez2  {} ", rez2).into());
-    console::log_1(&format!("-----------").into());
+    if a.abs() < f64::EPSILON {
+        // no relative movement, or the gap closes at a constant rate
+        if b >= 0.0 {
+            return None;
+        }
+        let t = -c / b;
+        return if t <= 1.0 { Some(t) } else { None };
+    }
 
-    if rez1 > 0.0 && rez1 < 1.0 {
-        return Some(rez1);
+    let d = b * b - 4.0 * a * c;
+    if d < 0.0 {
+        return None;
     }
 
-    if rez2 > 0.0 && rez2 < 1.0 {
-        return Some(rez2);
+    // c > 0, so the smaller root is the moment the balls start touching
+    let t = (-b - f64::sqrt(d)).div(2.0 * a);
+    if (0.0..=1.0).contains(&t) {
+        return Some(t);
     }
 
-    return None;
+    None
 }
@@ -0,0 +1,122 @@
+//! Plays a replay back natively and saves it as an animation, for sharing gameplay
+//! changes in code review.
+//!
+//!     circles-clip bug.replay --apng bug.png
+//!     circles-clip bug.replay --frames frames/ --every 1
+//!
+//! Exits with 2 on bad arguments or unreadable files.
+
+use circles::clip::{write_apng, ClipFrames, ClipOptions};
+use circles::replay::Replay;
+use std::fs;
+use std::io::BufWriter;
+use std::path::Path;
+use std::process;
+
+const USAGE: &str = "usage: circles-clip REPLAY [options]
+
+  --apng FILE    write an animated PNG that loops forever
+  --frames DIR   write every frame as DIR/frame-TICK.png, TICK is when it first shows
+  --every N      ticks between captured frames (default 2)
+  --hold N       ticks to go on once the last input played out (default 120)
+  --ticks N      stop after N ticks even if the level isn't decided (default 36000)
+
+At least one of --apng and --frames is needed. Identical frames in a row are written once.";
+
+struct Options {
+    replay: String,
+    apng: Option<String>,
+    frames: Option<String>,
+    clip: ClipOptions,
+}
+
+fn parse_args(args: &[String]) -> Result<Options, String> {
+    let mut replay = None;
+    let mut apng = None;
+    let mut frames = None;
+    let mut clip = ClipOptions::default();
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if arg == "--help" || arg == "-h" {
+            println!("{}", USAGE);
+            process::exit(0);
+        }
+        if !arg.starts_with("--") {
+            if replay.is_some() {
+                return Err(format!("unexpected argument '{}'", arg));
+            }
+            replay = Some(arg.clone());
+            continue;
+        }
+
+        let value = args
+            .next()
+            .ok_or_else(|| format!("{} needs a value", arg))?;
+        let number = || {
+            value
+                .parse::<usize>()
+                .map_err(|_| format!("{} expects a number, got '{}'", arg, value))
+        };
+
+        match arg.as_str() {
+            "--apng" => apng = Some(value.clone()),
+            "--frames" => frames = Some(value.clone()),
+            "--every" => clip.every = number()?.max(1),
+            "--hold" => clip.hold = number()?,
+            "--ticks" => clip.max_ticks = number()?,
+            _ => return Err(format!("unknown option '{}'", arg)),
+        }
+    }
+
+    let replay = replay.ok_or("missing the replay file")?;
+    if apng.is_none() && frames.is_none() {
+        return Err("nothing to write, pass --apng or --frames".to_string());
+    }
+    Ok(Options {
+        replay,
+        apng,
+        frames,
+        clip,
+    })
+}
+
+fn run(options: &Options) -> Result<(), String> {
+    let path = &options.replay;
+    let replay: Replay = fs::read_to_string(path)
+        .map_err(|err| format!("can't read {}: {}", path, err))?
+        .parse()
+        .map_err(|err| format!("{}: {}", path, err))?;
+
+    if let Some(dir) = &options.frames {
+        fs::create_dir_all(dir).map_err(|err| format!("can't create {}: {}", dir, err))?;
+        let mut count = 0;
+        for frame in ClipFrames::new(replay.clone(), options.clip) {
+            let file = Path::new(dir).join(format!("frame-{:06}.png", frame.tick));
+            let png = frame.raster.to_png().map_err(|err| err.to_string())?;
+            fs::write(&file, png)
+                .map_err(|err| format!("can't write {}: {}", file.display(), err))?;
+            count += 1;
+        }
+        println!("{} frames in {}", count, dir);
+    }
+
+    if let Some(file) = &options.apng {
+        let out = fs::File::create(file).map_err(|err| format!("can't write {}: {}", file, err))?;
+        let count = write_apng(&replay, options.clip, BufWriter::new(out))
+            .map_err(|err| format!("{}: {}", file, err))?;
+        println!("{} frames in {}", count, file);
+    }
+
+    Ok(())
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let result = parse_args(&args).and_then(|options| run(&options));
+
+    if let Err(err) = result {
+        eprintln!("circles-clip: {}\n\n{}", err, USAGE);
+        process::exit(2);
+    }
+}
@@ -0,0 +1,251 @@
+//! Runs a level natively, played by a bot or a replay, and prints how it went.
+//!
+//!     circles-sim --seed 42 --level 3 --bot greedy --format json
+//!     circles-sim --replay bug.replay --ticks 5000 --svg last-frame.svg
+//!     circles-sim --seed 1 --bot none --ticks 300 --png tick-300.png
+//!
+//! Exits with 2 on bad arguments or unreadable files, so shell loops can tell failures
+//! from lost levels.
+
+use circles::broadphase::BroadPhaseKind;
+use circles::levels::LevelPack;
+use circles::logic::{GameResult, GameState};
+use circles::player::{GreedyBot, Player, RandomBot};
+use circles::rendering::Renderer;
+use circles::replay::{Input, Playback, Replay};
+use circles::solver::{SolverBot, SolverConfig};
+use std::fs;
+use std::process;
+
+const USAGE: &str = "usage: circles-sim [options]
+
+  --seed N            layout seed (default 0)
+  --level N           1-based level to play (default 1)
+  --levels FILE       JSON level pack instead of the classic levels
+  --bot NAME          greedy, random, solver or none (default greedy)
+  --replay FILE       play a recorded replay instead of a bot, with the replay's seed and
+                      levels, so it can't be combined with --seed, --level, --levels or --bot
+  --ticks N           stop after N frames even if the level isn't decided (default 36000)
+  --broad-phase NAME  quadtree, brute-force, grid or sweep-and-prune
+  --format FORMAT     text or json (default text)
+  --svg FILE          write the last frame to FILE as an SVG image
+  --png FILE          write the last frame to FILE as a PNG image";
+
+struct Options {
+    seed: u64,
+    level: usize,
+    levels: Option<String>,
+    bot: String,
+    replay: Option<String>,
+    ticks: usize,
+    broad_phase: Option<BroadPhaseKind>,
+    json: bool,
+    svg: Option<String>,
+    png: Option<String>,
+}
+
+fn parse_args(args: &[String]) -> Result<Options, String> {
+    let mut options = Options {
+        seed: 0,
+        level: 1,
+        levels: None,
+        bot: "greedy".to_string(),
+        replay: None,
+        ticks: 36_000,
+        broad_phase: None,
+        json: false,
+        svg: None,
+        png: None,
+    };
+
+    // options that pick the game, a replay brings its own
+    let mut game_flags = vec![];
+
+    let mut args = args.iter();
+    while let Some(flag) = args.next() {
+        if flag == "--help" || flag == "-h" {
+            println!("{}", USAGE);
+            process::exit(0);
+        }
+
+        let value = args
+            .next()
+            .ok_or_else(|| format!("{} needs a value", flag))?;
+        let number = || {
+            value
+                .parse::<u64>()
+                .map_err(|_| format!("{} expects a number, got '{}'", flag, value))
+        };
+
+        if ["--seed", "--level", "--levels", "--bot"].contains(&flag.as_str()) {
+            game_flags.push(flag.as_str());
+        }
+        match flag.as_str() {
+            "--seed" => options.seed = number()?,
+            "--level" => options.level = number()? as usize,
+            "--levels" => options.levels = Some(value.clone()),
+            "--bot" => options.bot = value.clone(),
+            "--replay" => options.replay = Some(value.clone()),
+            "--ticks" => options.ticks = number()? as usize,
+            "--svg" => options.svg = Some(value.clone()),
+            "--png" => options.png = Some(value.clone()),
+            "--broad-phase" => options.broad_phase = Some(value.parse()?),
+            "--format" => {
+                options.json = match value.as_str() {
+                    "text" => false,
+                    "json" => true,
+                    _ => return Err(format!("unknown format '{}'", value)),
+                }
+            }
+            _ => return Err(format!("unknown option '{}'", flag)),
+        }
+    }
+
+    if options.replay.is_some() && !game_flags.is_empty() {
+        return Err(format!(
+            "--replay can't be combined with {}, the replay decides the game",
+            game_flags.join(", ")
+        ));
+    }
+    Ok(options)
+}
+
+fn read(path: &str) -> Result<String, String> {
+    fs::read_to_string(path).map_err(|err| format!("can't read {}: {}", path, err))
+}
+
+fn run(options: &Options) -> Result<(), String> {
+    let mut player: Option<Box<dyn Player>> = None;
+    let mut playback = None;
+
+    let mut game = match &options.replay {
+        Some(path) => {
+            let replay: Replay = read(path)?
+                .parse()
+                .map_err(|err| format!("{}: {}", path, err))?;
+            let recording = Playback::new(replay);
+            let game = recording.new_game();
+            playback = Some(recording);
+            game
+        }
+        None => {
+            let pack = match &options.levels {
+                Some(path) => LevelPack::from_json(&read(path)?)
+                    .map_err(|err| format!("{}: {}", path, err))?,
+                None => LevelPack::classic(),
+            };
+            if options.level == 0 || options.level > pack.levels.len() {
+                return Err(format!(
+                    "level {} doesn't exist, there are {} levels",
+                    options.level,
+                    pack.levels.len()
+                ));
+            }
+
+            player = match options.bot.as_str() {
+                "greedy" => Some(Box::new(GreedyBot)),
+                "random" => Some(Box::new(RandomBot::new(options.seed))),
+                "solver" => Some(Box::new(SolverBot::new(SolverConfig::offline()))),
+                "none" => None,
+                _ => return Err(format!("unknown bot '{}'", options.bot)),
+            };
+
+            let mut game = GameState::from_level_pack(&pack, options.seed);
+            if options.level != game.level_id {
+                game.start_level(options.level);
+            }
+            game
+        }
+    };
+    if let Some(kind) = options.broad_phase {
+        game.use_broad_phase(kind);
+    }
+
+    let mut frames = 0;
+    while frames < options.ticks {
+        match playback.as_mut() {
+            Some(recording) => {
+                recording.step(&mut game);
+                if recording.is_finished() {
+                    playback = None;
+                }
+            }
+            None => {
+                if game.result != GameResult::Playing {
+                    break;
+                }
+                if let Some(point) = player.as_mut().and_then(|player| player.act(&game)) {
+                    game.apply_input(Input::Click {
+                        x: point.x,
+                        y: point.y,
+                    });
+                }
+                game.advance();
+            }
+        }
+        frames += 1;
+    }
+
+    let result = match game.result {
+        GameResult::Playing => "undecided",
+        GameResult::Won => "won",
+        GameResult::Lost => "lost",
+    };
+    let player_name = match (&options.replay, &player) {
+        (Some(_), _) => "replay",
+        (None, Some(player)) => player.name(),
+        (None, None) => "none",
+    };
+    let shots_used = game.level().max_shots - game.shots;
+
+    if let Some(path) = &options.svg {
+        fs::write(path, Renderer::new(&game).to_svg(true))
+            .map_err(|err| format!("can't write {}: {}", path, err))?;
+    }
+    if let Some(path) = &options.png {
+        let png = Renderer::new(&game)
+            .to_png(true)
+            .map_err(|err| err.to_string())?;
+        fs::write(path, png).map_err(|err| format!("can't write {}: {}", path, err))?;
+    }
+
+    if options.json {
+        let report = serde_json::json!({
+            "seed": game.seed,
+            "level": game.level_id,
+            "player": player_name,
+            "result": result,
+            "captured": game.captured,
+            "required": game.captured_required,
+            "shots_used": shots_used,
+            "shots": game.level().max_shots,
+            "frames": frames,
+        });
+        println!("{}", report);
+    } else {
+        println!(
+            "seed {} level {} player {}: {}, captured {}/{}, shots used {}/{}, {} frames",
+            game.seed,
+            game.level_id,
+            player_name,
+            result,
+            game.captured,
+            game.captured_required,
+            shots_used,
+            game.level().max_shots,
+            frames
+        );
+    }
+
+    Ok(())
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let result = parse_args(&args).and_then(|options| run(&options));
+
+    if let Err(err) = result {
+        eprintln!("circles-sim: {}\n\n{}", err, USAGE);
+        process::exit(2);
+    }
+}
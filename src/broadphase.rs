@@ -0,0 +1,303 @@
+use crate::ball::{Ball, BallPairIds};
+use crate::geometry::{Rect, RenderingRect};
+use crate::quadtree::QuadTree;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::str::FromStr;
+
+// Finds pairs of balls that may be colliding, the narrow phase in `GameState` then
+// decides which of them really do. Candidates have to include every pair whose swept
+// bounding rects (`Ball::bounding_rect_swept`) overlap.
+pub trait BroadPhase: fmt::Debug {
+    // brings the structure in sync with this tick's balls
+    fn update(&mut self, rect: Rect, objects: &HashMap<usize, Ball>);
+
+    // every candidate pair once, in no particular order
+    fn candidates(&self) -> Vec<BallPairIds>;
+
+    // cells to draw in debug view
+    fn rectangles(&self) -> Vec<RenderingRect> {
+        vec![]
+    }
+
+    fn info_collisions(&self) -> Vec<String> {
+        vec![]
+    }
+
+    // lets `GameState` be cloned, e.g. to simulate ahead
+    fn clone_box(&self) -> Box<dyn BroadPhase>;
+}
+
+impl Clone for Box<dyn BroadPhase> {
+    fn clone(&self) -> Self {
+        self.clone_box()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BroadPhaseKind {
+    QuadTree,
+    BruteForce,
+    Grid,
+    SweepAndPrune,
+}
+
+impl BroadPhaseKind {
+    pub const ALL: [BroadPhaseKind; 4] = [
+        BroadPhaseKind::QuadTree,
+        BroadPhaseKind::BruteForce,
+        BroadPhaseKind::Grid,
+        BroadPhaseKind::SweepAndPrune,
+    ];
+
+    pub fn create(self, rect: Rect) -> Box<dyn BroadPhase> {
+        match self {
+            BroadPhaseKind::QuadTree => Box::new(QuadTree::new(rect)),
+            BroadPhaseKind::BruteForce => Box::new(BruteForce::default()),
+            BroadPhaseKind::Grid => Box::new(UniformGrid::new(GRID_CELL_SIZE)),
+            BroadPhaseKind::SweepAndPrune => Box::new(SweepAndPrune::default()),
+        }
+    }
+}
+
+impl fmt::Display for BroadPhaseKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            BroadPhaseKind::QuadTree => "quadtree",
+            BroadPhaseKind::BruteForce => "brute-force",
+            BroadPhaseKind::Grid => "grid",
+            BroadPhaseKind::SweepAndPrune => "sweep-and-prune",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for BroadPhaseKind {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        BroadPhaseKind::ALL
+            .iter()
+            .find(|kind| kind.to_string() == name)
+            .copied()
+            .ok_or_else(|| format!("unknown broad phase '{}'", name))
+    }
+}
+
+// keeps the quadtree up to date incrementally, see `QuadTree::move_ball`
+impl BroadPhase for QuadTree {
+    fn clone_box(&self) -> Box<dyn BroadPhase> {
+        Box::new(self.clone())
+    }
+
+    fn update(&mut self, rect: Rect, objects: &HashMap<usize, Ball>) {
+        if self.root().rect != rect {
+            self.clear(rect);
+        }
+
+        let vanished: Vec<usize> = self
+            .ball_ids()
+            .filter(|id| !objects.contains_key(id))
+            .collect();
+        for id in vanished {
+            self.remove_ball(id);
+        }
+
+        for ball in objects.values() {
+            self.move_ball(ball);
+        }
+
+        if self.check_incremental {
+            let mut rebuilt = QuadTree::new(rect);
+            for ball in objects.values() {
+                rebuilt.insert_ball(ball);
+            }
+            assert_eq!(
+                self.layout(),
+                rebuilt.layout(),
+                "incremental quadtree differs from a rebuilt one"
+            );
+        }
+    }
+
+    fn candidates(&self) -> Vec<BallPairIds> {
+        let pairs: HashSet<BallPairIds> = self.ball_ids().flat_map(|id| self.search(id)).collect();
+        pairs.into_iter().collect()
+    }
+
+    fn rectangles(&self) -> Vec<RenderingRect> {
+        self.get_rectangles()
+    }
+
+    fn info_collisions(&self) -> Vec<String> {
+        QuadTree::info_collisions(self)
+    }
+}
+
+// every pair of balls, the O(n²) reference the other ones are checked against
+#[derive(Debug, Clone, Default)]
+pub struct BruteForce {
+    ids: Vec<usize>,
+}
+
+impl BroadPhase for BruteForce {
+    fn clone_box(&self) -> Box<dyn BroadPhase> {
+        Box::new(self.clone())
+    }
+
+    fn update(&mut self, _rect: Rect, objects: &HashMap<usize, Ball>) {
+        self.ids.clear();
+        self.ids.extend(objects.keys());
+    }
+
+    fn candidates(&self) -> Vec<BallPairIds> {
+        let mut pairs = vec![];
+        for (i, first) in self.ids.iter().enumerate() {
+            for second in &self.ids[i + 1..] {
+                pairs.push(BallPairIds {
+                    first: *first,
+                    second: *second,
+                });
+            }
+        }
+        pairs
+    }
+}
+
+// a bit more than the diameter of the biggest regular ball
+pub const GRID_CELL_SIZE: f64 = 64.0;
+
+// Spatial hash with square cells, a ball is put in every cell its swept bounding rect touches.
+#[derive(Debug, Clone)]
+pub struct UniformGrid {
+    cell_size: f64,
+    // cells keep their vecs between frames, emptied ones are skipped
+    cells: HashMap<(i64, i64), Vec<usize>>,
+}
+
+impl UniformGrid {
+    pub fn new(cell_size: f64) -> Self {
+        Self {
+            cell_size,
+            cells: HashMap::new(),
+        }
+    }
+
+    fn cell_of(&self, x: f64, y: f64) -> (i64, i64) {
+        (
+            (x / self.cell_size).floor() as i64,
+            (y / self.cell_size).floor() as i64,
+        )
+    }
+}
+
+impl BroadPhase for UniformGrid {
+    fn clone_box(&self) -> Box<dyn BroadPhase> {
+        Box::new(self.clone())
+    }
+
+    fn update(&mut self, _rect: Rect, objects: &HashMap<usize, Ball>) {
+        for cell in self.cells.values_mut() {
+            cell.clear();
+        }
+
+        for ball in objects.values() {
+            let bb = ball.bounding_rect_swept();
+            let (min_x, min_y) = self.cell_of(bb.x, bb.y);
+            let (max_x, max_y) = self.cell_of(bb.x + bb.w, bb.y + bb.h);
+
+            for x in min_x..=max_x {
+                for y in min_y..=max_y {
+                    self.cells.entry((x, y)).or_default().push(ball.id);
+                }
+            }
+        }
+    }
+
+    fn candidates(&self) -> Vec<BallPairIds> {
+        let mut pairs: HashSet<BallPairIds> = HashSet::new();
+        for cell in self.cells.values() {
+            for (i, first) in cell.iter().enumerate() {
+                for second in &cell[i + 1..] {
+                    pairs.insert(BallPairIds {
+                        first: *first,
+                        second: *second,
+                    });
+                }
+            }
+        }
+        pairs.into_iter().collect()
+    }
+
+    fn rectangles(&self) -> Vec<RenderingRect> {
+        self.cells
+            .iter()
+            .filter(|(_, balls)| !balls.is_empty())
+            .map(|((x, y), balls)| RenderingRect {
+                rect: Rect {
+                    x: *x as f64 * self.cell_size,
+                    y: *y as f64 * self.cell_size,
+                    w: self.cell_size,
+                    h: self.cell_size,
+                },
+                many: balls.len() > 1,
+            })
+            .collect()
+    }
+}
+
+// Sorts swept bounding rects by their left edge and pairs the ones overlapping on both
+// axes. Balls barely move between ticks, so the insertion sort on last frame's order is
+// close to linear.
+#[derive(Debug, Clone, Default)]
+pub struct SweepAndPrune {
+    boxes: Vec<(usize, Rect)>,
+}
+
+impl BroadPhase for SweepAndPrune {
+    fn clone_box(&self) -> Box<dyn BroadPhase> {
+        Box::new(self.clone())
+    }
+
+    fn update(&mut self, _rect: Rect, objects: &HashMap<usize, Ball>) {
+        self.boxes.retain(|(id, _)| objects.contains_key(id));
+        for (id, bb) in self.boxes.iter_mut() {
+            *bb = objects[id].bounding_rect_swept();
+        }
+
+        let known: HashSet<usize> = self.boxes.iter().map(|(id, _)| *id).collect();
+        for ball in objects.values() {
+            if !known.contains(&ball.id) {
+                self.boxes.push((ball.id, ball.bounding_rect_swept()));
+            }
+        }
+
+        for i in 1..self.boxes.len() {
+            let mut j = i;
+            while j > 0 && self.boxes[j - 1].1.x > self.boxes[j].1.x {
+                self.boxes.swap(j - 1, j);
+                j -= 1;
+            }
+        }
+    }
+
+    fn candidates(&self) -> Vec<BallPairIds> {
+        let mut pairs = vec![];
+        for (i, (first, first_box)) in self.boxes.iter().enumerate() {
+            for (second, second_box) in &self.boxes[i + 1..] {
+                if second_box.x > first_box.x + first_box.w {
+                    break;
+                }
+                if second_box.y <= first_box.y + first_box.h
+                    && first_box.y <= second_box.y + second_box.h
+                {
+                    pairs.push(BallPairIds {
+                        first: *first,
+                        second: *second,
+                    });
+                }
+            }
+        }
+        pairs
+    }
+}
@@ -0,0 +1,48 @@
+use crate::ball::Color;
+use crate::draw::{Draw, Stroke};
+use crate::geometry::{Point, Rect};
+use std::f64::consts::PI;
+use web_sys::{console, CanvasRenderingContext2d};
+
+// draws straight to the browser canvas
+impl Draw for CanvasRenderingContext2d {
+    fn clear(&mut self, rect: Rect) {
+        self.clear_rect(rect.x, rect.y, rect.w, rect.h);
+    }
+
+    fn alpha(&mut self, alpha: f64) {
+        self.set_global_alpha(alpha);
+    }
+
+    fn circle(&mut self, center: Point, radius: f64, fill: Option<Color>, stroke: Option<Stroke>) {
+        self.begin_path();
+        if self.arc(center.x, center.y, radius, 0.0, 2.0 * PI).is_err() {
+            console::log_1(&format!("error drawing circle with radius: {}", radius).into());
+        }
+        if let Some(color) = fill {
+            self.set_fill_style_str(&color.to_string());
+            self.fill();
+        }
+        if let Some(stroke) = stroke {
+            self.set_line_width(stroke.width);
+            self.set_stroke_style_str(&stroke.color.to_string());
+            self.stroke();
+        }
+        self.close_path();
+    }
+
+    fn rect(&mut self, rect: Rect, color: Color) {
+        self.begin_path();
+        self.set_line_width(1.0);
+        self.set_stroke_style_str(&color.to_string());
+        self.stroke_rect(rect.x, rect.y, rect.w, rect.h);
+    }
+
+    fn text(&mut self, x: f64, y: f64, text: &str, color: Color) {
+        self.set_font("14px Verdana");
+        self.set_fill_style_str(&color.to_string());
+        if self.fill_text(text, x, y).is_err() {
+            console::log_1(&format!("error filling text: {}", text).into());
+        }
+    }
+}
@@ -0,0 +1,184 @@
+use crate::ball::BallState::{Expanding, Shrinking};
+use crate::constants::TICKS_PER_SECOND;
+use crate::logic::{GameResult, GameState};
+use crate::raster::{PngError, Raster};
+use crate::rendering::Renderer;
+use crate::replay::{Playback, Replay};
+use std::io::Write;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ClipOptions {
+    // ticks between two captured frames
+    pub every: usize,
+    // ticks the clip goes on once the replay ran out and nothing is being captured,
+    // so the won or lost screen stays for a bit at the end
+    pub hold: usize,
+    // stops after this many ticks even if the level isn't decided
+    pub max_ticks: usize,
+}
+
+impl Default for ClipOptions {
+    fn default() -> Self {
+        Self {
+            every: 2,
+            hold: 120,
+            max_ticks: 36_000,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ClipFrame {
+    pub raster: Raster,
+    // tick at which the frame first shows, counted from the start of the replay
+    pub tick: usize,
+    // how many ticks it stays
+    pub ticks: usize,
+}
+
+// Plays a replay back natively and renders it the way the page would, won and lost
+// screens included, until `hold` ticks after the replay's last input played out.
+// Identical frames in a row, like a paused game, come out as one longer frame.
+pub struct ClipFrames {
+    playback: Option<Playback>,
+    game: GameState,
+    options: ClipOptions,
+    tick: usize,
+    // ticks since the replay ran out and nothing was being captured
+    held: usize,
+    pending: Option<ClipFrame>,
+}
+
+impl ClipFrames {
+    pub fn new(replay: Replay, options: ClipOptions) -> Self {
+        let playback = Playback::new(replay);
+        let game = playback.new_game();
+        Self {
+            playback: Some(playback),
+            game,
+            options,
+            tick: 0,
+            held: 0,
+            pending: None,
+        }
+    }
+
+    pub fn game(&self) -> &GameState {
+        &self.game
+    }
+
+    fn is_done(&self) -> bool {
+        self.tick >= self.options.max_ticks || self.held >= self.options.hold.max(1)
+    }
+
+    fn step(&mut self) {
+        match self.playback.as_mut() {
+            Some(playback) => {
+                playback.step(&mut self.game);
+                if playback.is_finished() {
+                    self.playback = None;
+                }
+            }
+            None => self.game.advance(),
+        }
+        // without input, a level only changes while capture balls are around
+        let capturing = self.game.result == GameResult::Playing
+            && self
+                .game
+                .objects
+                .values()
+                .any(|ball| ball.ball_state == Expanding || ball.ball_state == Shrinking);
+        if self.playback.is_none() && !capturing {
+            self.held += 1;
+        } else {
+            self.held = 0;
+        }
+        self.tick += 1;
+    }
+
+    fn render(&self) -> Raster {
+        let renderer = Renderer::new(&self.game);
+        let mut raster = Raster::new(self.game.rect);
+        match self.game.result {
+            GameResult::Playing => renderer.render_state(&mut raster, false),
+            GameResult::Won => renderer.render_won(&mut raster),
+            GameResult::Lost => renderer.render_lost(&mut raster),
+        }
+        raster
+    }
+}
+
+impl Iterator for ClipFrames {
+    type Item = ClipFrame;
+
+    fn next(&mut self) -> Option<ClipFrame> {
+        while !self.is_done() {
+            let tick = self.tick;
+            let mut ticks = 0;
+            while ticks < self.options.every.max(1) && !self.is_done() {
+                self.step();
+                ticks += 1;
+            }
+
+            let raster = self.render();
+            match self.pending.as_mut() {
+                Some(pending) if pending.raster == raster => pending.ticks += ticks,
+                _ => {
+                    let frame = ClipFrame {
+                        raster,
+                        tick,
+                        ticks,
+                    };
+                    if let Some(done) = self.pending.replace(frame) {
+                        return Some(done);
+                    }
+                }
+            }
+        }
+        self.pending.take()
+    }
+}
+
+// Writes the replay as an animated PNG that loops forever and returns the number of
+// frames. The header needs the frame count up front, so the replay is played once and the
+// frames are kept as compressed PNGs until it's known, a few kilobytes each instead of a
+// full RGB buffer.
+pub fn write_apng<W: Write>(
+    replay: &Replay,
+    options: ClipOptions,
+    out: W,
+) -> Result<usize, PngError> {
+    let mut frames = vec![];
+    for frame in ClipFrames::new(replay.clone(), options) {
+        let size = (frame.raster.width(), frame.raster.height());
+        frames.push((frame.raster.to_png()?, frame.ticks, size));
+    }
+    let (width, height) = match frames.first() {
+        Some((_, _, size)) => *size,
+        None => return Err(PngError::Encoding("the replay has no frames".to_string())),
+    };
+
+    let encoding = |err: png::EncodingError| PngError::Encoding(err.to_string());
+    let decoding = |err: png::DecodingError| PngError::Encoding(err.to_string());
+    let mut encoder = png::Encoder::new(out, width as u32, height as u32);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder
+        .set_animated(frames.len() as u32, 0)
+        .map_err(encoding)?;
+    let mut writer = encoder.write_header().map_err(encoding)?;
+    let mut rgb = vec![];
+    for (png, ticks, _) in &frames {
+        let mut reader = png::Decoder::new(&png[..]).read_info().map_err(decoding)?;
+        rgb.resize(reader.output_buffer_size(), 0);
+        reader.next_frame(&mut rgb).map_err(decoding)?;
+
+        let ticks = (*ticks).min(u16::MAX as usize) as u16;
+        writer
+            .set_frame_delay(ticks, TICKS_PER_SECOND as u16)
+            .map_err(encoding)?;
+        writer.write_image_data(&rgb).map_err(encoding)?;
+    }
+    writer.finish().map_err(encoding)?;
+    Ok(frames.len())
+}
@@ -8,11 +8,18 @@ pub const LARGE: f64 = 25.0;
 pub const FULL: f64 = 50.0;
 pub const COLOSSAL: f64 = 75.0;
 
-pub const SLOW: f64 = 1.0;
-pub const FAST: f64 = 3.0;
+// The simulation always advances in ticks of 1 / TICKS_PER_SECOND seconds, however
+// often the display refreshes. Speeds below are per second.
+pub const TICKS_PER_SECOND: f64 = 60.0;
 
-pub const GROWS: f64 = 1.0;
-pub const EXPLODES: f64 = 9.0;
+// ball speeds, in units per second
+pub const SLOW: f64 = 60.0;
+pub const FAST: f64 = 180.0;
 
-// how fast the active ball shrinks after reaching FULL size
-pub const SHRINK: f64 = -15.0;
+// how fast a captured ball's radius grows, in units per second
+pub const GROWS: f64 = 60.0;
+pub const EXPLODES: f64 = 540.0;
+
+// how fast the active ball shrinks after reaching FULL size, per second and divided by
+// its radius, so small balls vanish quicker
+pub const SHRINK: f64 = -900.0;
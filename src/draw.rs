@@ -0,0 +1,119 @@
+use crate::ball::Color;
+use crate::geometry::{Point, Rect};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Stroke {
+    pub color: Color,
+    pub width: f64,
+}
+
+// The few drawing operations `Renderer` needs, so it can draw to a browser canvas or
+// anything else. Coordinates are arena pixels.
+pub trait Draw {
+    // erases everything inside `rect`
+    fn clear(&mut self, rect: Rect);
+
+    // opacity of everything drawn after this call, 0 to 1
+    fn alpha(&mut self, alpha: f64);
+
+    // filled and outlined circle, either can be left out
+    fn circle(&mut self, center: Point, radius: f64, fill: Option<Color>, stroke: Option<Stroke>);
+
+    // outline of a rectangle
+    fn rect(&mut self, rect: Rect, color: Color);
+
+    // single line of text, `y` is the baseline
+    fn text(&mut self, x: f64, y: f64, text: &str, color: Color);
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DrawCall {
+    Clear(Rect),
+    Alpha(f64),
+    Circle {
+        center: Point,
+        radius: f64,
+        fill: Option<Color>,
+        stroke: Option<Stroke>,
+    },
+    Rect(Rect, Color),
+    Text {
+        x: f64,
+        y: f64,
+        text: String,
+        color: Color,
+    },
+}
+
+// keeps the calls instead of drawing them, for tests and exporters
+#[derive(Debug, Clone, Default)]
+pub struct Recording {
+    pub calls: Vec<DrawCall>,
+}
+
+impl Recording {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // all texts drawn, in order
+    pub fn texts(&self) -> Vec<&str> {
+        self.calls
+            .iter()
+            .filter_map(|call| match call {
+                DrawCall::Text { text, .. } => Some(text.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    // replays the recorded calls onto another target
+    pub fn replay(&self, target: &mut dyn Draw) {
+        for call in &self.calls {
+            match call {
+                DrawCall::Clear(rect) => target.clear(*rect),
+                DrawCall::Alpha(alpha) => target.alpha(*alpha),
+                DrawCall::Circle {
+                    center,
+                    radius,
+                    fill,
+                    stroke,
+                } => target.circle(*center, *radius, *fill, *stroke),
+                DrawCall::Rect(rect, color) => target.rect(*rect, *color),
+                DrawCall::Text { x, y, text, color } => target.text(*x, *y, text, *color),
+            }
+        }
+    }
+}
+
+impl Draw for Recording {
+    fn clear(&mut self, rect: Rect) {
+        self.calls.push(DrawCall::Clear(rect));
+    }
+
+    fn alpha(&mut self, alpha: f64) {
+        self.calls.push(DrawCall::Alpha(alpha));
+    }
+
+    fn circle(&mut self, center: Point, radius: f64, fill: Option<Color>, stroke: Option<Stroke>) {
+        self.calls.push(DrawCall::Circle {
+            center,
+            radius,
+            fill,
+            stroke,
+        });
+    }
+
+    fn rect(&mut self, rect: Rect, color: Color) {
+        self.calls.push(DrawCall::Rect(rect, color));
+    }
+
+    fn text(&mut self, x: f64, y: f64, text: &str, color: Color) {
+        self.calls.push(DrawCall::Text {
+            x,
+            y,
+            text: text.to_string(),
+            color,
+        });
+    }
+}
@@ -0,0 +1,160 @@
+use crate::ball::Ball;
+use crate::levels::LevelPack;
+use crate::logic::{GameResult, GameState};
+use crate::observation::{Encoded, Encoding};
+use crate::replay::Input;
+
+// what the agent does at the start of a step
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Action {
+    NoOp,
+    // shoots a capture ball, same as a mouse click at arena coordinates
+    Click { x: f64, y: f64 },
+}
+
+// snapshot of the game after a step, balls are ordered by id
+#[derive(Debug, Clone)]
+pub struct Observation {
+    pub level_id: usize,
+    pub frame_id: usize,
+    pub shots: usize,
+    pub captured: usize,
+    pub captured_required: usize,
+    pub balls: Vec<Ball>,
+    // the balls in the environment's encoding
+    pub features: Encoded,
+}
+
+impl Observation {
+    pub fn from_game(game: &GameState, encoding: Encoding) -> Self {
+        let mut balls: Vec<Ball> = game.objects.values().copied().collect();
+        balls.sort_by_key(|ball| ball.id);
+
+        Self {
+            level_id: game.level_id,
+            frame_id: game.frame_id,
+            shots: game.shots,
+            captured: game.captured,
+            captured_required: game.captured_required,
+            balls,
+            features: game.observe(encoding),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Step {
+    pub observation: Observation,
+    // balls captured during the step, capture balls the agent shot itself don't count
+    pub reward: f64,
+    // the level is won or lost, call `reset` to play again
+    pub done: bool,
+    pub result: GameResult,
+}
+
+// Gym-style wrapper for training agents natively, one episode is one level:
+//
+//   let mut env = Env::new(4);
+//   let mut observation = env.reset(seed, 1);
+//   loop {
+//       let step = env.step(agent.act(&observation));
+//       if step.done { break; }
+//       observation = step.observation;
+//   }
+#[derive(Debug)]
+pub struct Env {
+    pack: LevelPack,
+    game: GameState,
+    // frames simulated per step
+    frame_skip: usize,
+    encoding: Encoding,
+}
+
+impl Env {
+    pub fn new(frame_skip: usize) -> Self {
+        Self::from_level_pack(LevelPack::classic(), frame_skip)
+    }
+
+    pub fn from_level_pack(pack: LevelPack, frame_skip: usize) -> Self {
+        assert!(
+            frame_skip > 0,
+            "every step has to advance at least one frame"
+        );
+        let game = GameState::from_level_pack(&pack, 0);
+        Self {
+            pack,
+            game,
+            frame_skip,
+            encoding: Encoding::Raw,
+        }
+    }
+
+    pub fn frame_skip(&self) -> usize {
+        self.frame_skip
+    }
+
+    pub fn set_frame_skip(&mut self, frame_skip: usize) {
+        assert!(
+            frame_skip > 0,
+            "every step has to advance at least one frame"
+        );
+        self.frame_skip = frame_skip;
+    }
+
+    pub fn encoding(&self) -> Encoding {
+        self.encoding
+    }
+
+    pub fn set_encoding(&mut self, encoding: Encoding) {
+        self.encoding = encoding;
+    }
+
+    pub fn game(&self) -> &GameState {
+        &self.game
+    }
+
+    pub fn num_levels(&self) -> usize {
+        self.pack.levels.len()
+    }
+
+    // starts the 1-based `level` from scratch, the layout only depends on seed and level
+    pub fn reset(&mut self, seed: u64, level: usize) -> Observation {
+        self.game = GameState::from_level_pack(&self.pack, seed);
+        if level != self.game.level_id {
+            self.game.start_level(level);
+        }
+        Observation::from_game(&self.game, self.encoding)
+    }
+
+    // applies the action, then simulates `frame_skip` frames or until the level ends
+    pub fn step(&mut self, action: Action) -> Step {
+        let before = self.captured_balls();
+
+        if let Action::Click { x, y } = action {
+            if self.game.result == GameResult::Playing {
+                self.game.apply_input(Input::Click { x, y });
+            }
+        }
+
+        for _ in 0..self.frame_skip {
+            if self.game.result != GameResult::Playing {
+                break;
+            }
+            self.game.advance();
+        }
+
+        let result = self.game.check_win_lose();
+        Step {
+            observation: Observation::from_game(&self.game, self.encoding),
+            reward: self.captured_balls().saturating_sub(before) as f64,
+            done: result != GameResult::Playing,
+            result,
+        }
+    }
+
+    // `GameState::captured` also counts the capture balls, leave those out
+    fn captured_balls(&self) -> usize {
+        let shots_used = self.game.level().max_shots - self.game.shots;
+        self.game.captured.saturating_sub(shots_used)
+    }
+}
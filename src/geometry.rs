@@ -1,7 +1,7 @@
 use std::fmt;
 use crate::ball::is_point_in_rect;
 use crate::geometry::Cells::{BottomLeft, BottomRight, TopLeft, TopRight};
-use crate::random::{random_range, random_sign, random_velocity};
+use crate::random::Random;
 
 
 pub enum Cells {
@@ -18,7 +18,7 @@ pub struct RenderingRect {
     pub many: bool,
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub struct Rect {
     pub x: f64,
     pub y: f64,
@@ -40,11 +40,19 @@ impl Rect {
         false
     }
 
+    // like `fits`, but touching an edge of `other` doesn't count
+    pub fn fits_inside(&self, other: Rect) -> bool {
+        self.x > other.x
+            && self.y > other.y
+            && self.x + self.w < other.x + other.w
+            && self.y + self.h < other.y + other.h
+    }
+
     pub fn fits(&self, other: Rect) -> bool {
         let top_left = Point { x: self.x, y: self.y }.is_in_rect(&other);
         let top_right = Point { x: self.x + self.w, y: self.y }.is_in_rect(&other);
-        let bottom_left = Point { x: self.x, y: self.y - self.h }.is_in_rect(&other);
-        let bottom_right = Point { x: self.x + self.w, y: self.y - self.h }.is_in_rect(&other);
+        let bottom_left = Point { x: self.x, y: self.y + self.h }.is_in_rect(&other);
+        let bottom_right = Point { x: self.x + self.w, y: self.y + self.h }.is_in_rect(&other);
 
         if top_left && top_right && bottom_left && bottom_right {
             return true;
@@ -62,7 +70,7 @@ impl Rect {
         let bottom_left = Rect { x: self.x, y: self.y + new_h, w: new_w, h: new_h };
         let bottom_right = Rect { x: self.x + new_w, y: self.y + new_h, w: new_w, h: new_h };
 
-        return (top_left, top_right, bottom_left, bottom_right);
+        (top_left, top_right, bottom_left, bottom_right)
     }
 
     pub fn center(&self) -> Point {
@@ -77,16 +85,14 @@ impl Rect {
 
         if center.x < point.x {
             if center.y < point.y {
-                return BottomRight;
+                BottomRight
             } else {
-                return TopRight;
+                TopRight
             }
         } else if center.y < point.y {
-            //console::log_1(&format!("where is point? {:#?} {:#?}, {:#?} ", self, point, center).into());
-
-            return BottomLeft;
+            BottomLeft
         } else {
-            return TopLeft;
+            TopLeft
         }
     }
 
@@ -131,31 +137,21 @@ impl fmt::Display for Point {
 }
 
 impl Point {
-    pub fn random_point(width: usize, height: usize) -> Self {
+    pub fn random_point(rng: &mut Random, width: usize, height: usize) -> Self {
         Self {
-            x: random_range(width / 10, 9 * width / 10) as f64,
-            y: random_range(width / 10, 9 * height / 10) as f64,
+            x: rng.random_range(width / 10, 9 * width / 10) as f64,
+            y: rng.random_range(height / 10, 9 * height / 10) as f64,
 
         }
     }
-    pub fn random_velocity(min: f64, max: f64) -> Self {
+    pub fn random_velocity(rng: &mut Random, min: f64, max: f64) -> Self {
         Self {
-            x: random_sign() * random_velocity(min, max),
-            y: random_sign() * random_velocity(min, max),
+            x: rng.random_sign() * rng.random_velocity(min, max),
+            y: rng.random_sign() * rng.random_velocity(min, max),
         }
     }
 
     pub fn is_in_rect(&self, rect: &Rect) -> bool {
-        return is_point_in_rect(rect.x, rect.y, rect.w, rect.h, self.x, self.y);
-    }
-
-    fn is_point_in_rect(x: f64, y: f64, w: f64, h: f64, point_x: f64, point_y: f64) -> bool {
-        if point_x > x
-            && point_x < x + w
-            && point_y > y
-            && point_y < y + h {
-            return true;
-        }
-        false
+        is_point_in_rect(rect.x, rect.y, rect.w, rect.h, self.x, self.y)
     }
 }
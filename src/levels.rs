@@ -0,0 +1,215 @@
+use crate::ball::BallType;
+use crate::random::Random;
+use serde::Deserialize;
+use std::fmt;
+
+const CLASSIC: &str = include_str!("../levels/classic.json");
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Level {
+    pub max_shots: usize,
+    pub num_of_balls: usize,
+    pub num_captured: usize,
+    // ball types spawned in this level with their relative weights,
+    // empty means the default mix from `BallType::random_ball_type`
+    pub ball_types: Vec<(BallType, f64)>,
+}
+
+impl Level {
+    pub fn new(max_shots: usize, num_of_balls: usize, num_captured: usize) -> Self {
+        Self {
+            max_shots,
+            num_of_balls,
+            num_captured,
+            ball_types: vec![],
+        }
+    }
+
+    // whether the level can be played, for levels that don't come from the game itself
+    pub fn validate(&self) -> Result<(), LevelError> {
+        if self.max_shots == 0 {
+            return Err(LevelError::NoShots);
+        }
+        if self.num_of_balls == 0 {
+            return Err(LevelError::NoBalls);
+        }
+        // active balls count as captured too
+        if self.num_captured > self.num_of_balls + self.max_shots {
+            return Err(LevelError::CaptureTooHigh(
+                self.num_captured,
+                self.num_of_balls + self.max_shots,
+            ));
+        }
+        for (ball_type, weight) in &self.ball_types {
+            if !weight.is_finite() || *weight <= 0.0 {
+                return Err(LevelError::InvalidWeight(
+                    ball_type.name().to_string(),
+                    *weight,
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn random_ball_type(&self, rng: &mut Random) -> BallType {
+        if self.ball_types.is_empty() {
+            return BallType::random_ball_type(rng);
+        }
+
+        let weights: Vec<f64> = self.ball_types.iter().map(|(_, weight)| *weight).collect();
+        self.ball_types[rng.weighted_index(&weights)].0
+    }
+}
+
+// A campaign of levels played in order, loaded from JSON:
+//
+//   {
+//     "name": "classic",
+//     "arena": { "width": 640, "height": 480 },
+//     "levels": [
+//       { "shots": 1, "balls": 5, "capture": 2 },
+//       { "shots": 2, "balls": 30, "capture": 25,
+//         "ball_types": [{ "type": "RED_BALL", "weight": 3 }, { "type": "SILVER_BALL", "weight": 1 }] }
+//     ]
+//   }
+#[derive(Debug, Clone)]
+pub struct LevelPack {
+    pub name: String,
+    pub width: usize,
+    pub height: usize,
+    pub levels: Vec<Level>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PackFile {
+    name: String,
+    arena: ArenaFile,
+    levels: Vec<LevelFile>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ArenaFile {
+    width: usize,
+    height: usize,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LevelFile {
+    shots: usize,
+    balls: usize,
+    capture: usize,
+    #[serde(default)]
+    ball_types: Vec<BallTypeFile>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BallTypeFile {
+    #[serde(rename = "type")]
+    name: String,
+    weight: f64,
+}
+
+impl LevelPack {
+    // the levels the game ships with
+    pub fn classic() -> Self {
+        Self::from_json(CLASSIC).expect("classic level pack should be valid")
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, LevelPackError> {
+        let file: PackFile =
+            serde_json::from_str(json).map_err(|err| LevelPackError::Parse(err.to_string()))?;
+
+        if file.arena.width == 0 || file.arena.height == 0 {
+            return Err(LevelPackError::InvalidArena(
+                file.arena.width,
+                file.arena.height,
+            ));
+        }
+        if file.levels.is_empty() {
+            return Err(LevelPackError::NoLevels);
+        }
+
+        let levels = file
+            .levels
+            .iter()
+            .enumerate()
+            .map(|(index, level)| {
+                parse_level(level).map_err(|err| LevelPackError::Level(index + 1, err))
+            })
+            .collect::<Result<Vec<Level>, LevelPackError>>()?;
+
+        Ok(Self {
+            name: file.name,
+            width: file.arena.width,
+            height: file.arena.height,
+            levels,
+        })
+    }
+}
+
+fn parse_level(file: &LevelFile) -> Result<Level, LevelError> {
+    let mut level = Level::new(file.shots, file.balls, file.capture);
+    for entry in &file.ball_types {
+        let ball_type = BallType::from_name(&entry.name)
+            .ok_or_else(|| LevelError::UnknownBallType(entry.name.clone()))?;
+        level.ball_types.push((ball_type, entry.weight));
+    }
+
+    level.validate()?;
+    Ok(level)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LevelPackError {
+    Parse(String),
+    InvalidArena(usize, usize),
+    NoLevels,
+    // 1-based level number and what is wrong with it
+    Level(usize, LevelError),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LevelError {
+    NoShots,
+    NoBalls,
+    CaptureTooHigh(usize, usize),
+    UnknownBallType(String),
+    InvalidWeight(String, f64),
+}
+
+impl fmt::Display for LevelPackError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LevelPackError::Parse(err) => write!(f, "can't parse level pack: {}", err),
+            LevelPackError::InvalidArena(width, height) => {
+                write!(f, "arena {}x{} should not be empty", width, height)
+            }
+            LevelPackError::NoLevels => write!(f, "level pack has no levels"),
+            LevelPackError::Level(level, err) => write!(f, "level {}: {}", level, err),
+        }
+    }
+}
+
+impl fmt::Display for LevelError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LevelError::NoShots => write!(f, "needs at least one shot"),
+            LevelError::NoBalls => write!(f, "needs at least one ball"),
+            LevelError::CaptureTooHigh(capture, max) => write!(
+                f,
+                "can't capture {} balls, at most {} can be captured",
+                capture, max
+            ),
+            LevelError::UnknownBallType(name) => write!(f, "unknown ball type '{}'", name),
+            LevelError::InvalidWeight(name, weight) => {
+                write!(f, "weight {} of '{}' should be positive", weight, name)
+            }
+        }
+    }
+}
+
+impl std::error::Error for LevelPackError {}
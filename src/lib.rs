@@ -1,38 +1,75 @@
-mod ball;
-mod constants;
-mod geometry;
-mod logic;
-mod quadtree;
-mod random;
-mod rendering;
-mod utils;
+// platform-free simulation core, builds and runs natively
+pub mod ball;
+pub mod broadphase;
+pub mod clip;
+pub mod constants;
+pub mod draw;
+pub mod env;
+pub mod geometry;
+pub mod levels;
+pub mod logic;
+pub mod observation;
+pub mod player;
+pub mod quadtree;
+pub mod random;
+pub mod raster;
+pub mod rendering;
+pub mod replay;
+pub mod solver;
+pub mod svg;
+pub mod timestep;
+pub mod viewport;
+
+// wasm/canvas shell on top of the core
+pub mod canvas;
+pub mod utils;
 
 use logic::GameState;
 
 use js_sys::Function;
 use rendering::Renderer;
+use timestep::FixedStep;
+use viewport::Viewport;
 use std::cell::RefCell;
 use std::rc::Rc;
 use wasm_bindgen::prelude::*;
 use wasm_bindgen::JsCast;
-use web_sys::{console, HtmlElement, KeyboardEvent, MouseEvent};
+use web_sys::{console, Event, HtmlElement, KeyboardEvent, MouseEvent};
 
+use crate::levels::LevelPack;
 use crate::logic::ChangeState;
 use crate::logic::GameResult;
-use crate::rendering::write_text;
-use crate::utils::{document, get_context, get_debug_context, request_animation_frame, window};
+use crate::random::Random;
+use crate::replay::{Input, Playback, Replay, ReplayError};
+use crate::geometry::Point;
+use crate::solver::{captured_by_click, solve, Shot, SolverConfig};
+use crate::utils::{
+    document, get_canvas, get_context, request_animation_frame, set_panic_hook, window,
+};
+
+// Ticks the capture preview simulates ahead at most, 5 seconds. It runs on the UI thread
+// once per tick while the mouse is over the game, so longer chains are cut short.
+const PREVIEW_HORIZON: usize = 300;
+
+// level, shots left and mouse position
+type PreviewKey = (usize, usize, Point);
 
 thread_local! {
+    // the arena is as big as the canvas element on the page
     static GAME: Rc<RefCell<GameState>> = Rc::new(
         RefCell::new(
             GameState::new(
-                constants::WIDTH,
-                constants::HEIGHT))
+                get_canvas().width() as usize,
+                get_canvas().height() as usize))
     );
 
 
     static HANDLE_KEYDOWN: Closure<dyn FnMut(KeyboardEvent)> =
     Closure::wrap(Box::new(|evt: KeyboardEvent| GAME.with(|game_obj| {
+        // any key puts the hint away again
+        if hide_hint(&mut game_obj.borrow_mut()) {
+            return;
+        }
 
         let change: ChangeState = match &evt.key()[..] {
                 "G" | "g" => ChangeState::PlayPause,
@@ -46,59 +83,253 @@ thread_local! {
                 "R" | "r" => {
                     ChangeState::RestartLevel
                 }
+                "H" | "h" => {
+                    if !is_playing_back() {
+                        show_hint(&mut game_obj.borrow_mut());
+                    }
+                    ChangeState::NoChange
+                }
 
                 _=> ChangeState::NoChange
        };
-    let game = game_obj.borrow_mut();
-        match change {
-            ChangeState::PlayPause => {
-                game.pause_play();
-            },
-            ChangeState::NextLevel => {
-                game.next_level();
-            },
-            ChangeState::RestartLevel => {
-                game.restart();
-            }
-            ChangeState::Quit => {
-                game.quit();
-            },
-    ChangeState::NoChange => {
-
-            },
-}
+        if !is_playing_back() {
+            game_obj.borrow_mut().apply_input(Input::Change(change));
+        }
 
     })) as Box<dyn FnMut(KeyboardEvent)>);
 
 
     static HANDLE_MOUSE: Closure<dyn FnMut(MouseEvent)> =
     Closure::wrap(Box::new(|evt: MouseEvent| GAME.with(|game| {
-        let clicked = match &evt.button() {
-            0 => true,
-            _ => false,
-            };
+        hide_hint(&mut game.borrow_mut());
+        let clicked = evt.button() == 0;
         let game_result: GameResult = game.borrow().check_win_lose();
-        if clicked {
-            match game_result {
-                GameResult::Playing => {
-                  //  game.borrow_mut().create_capture_ball(evt.client_x() as f64,evt.client_y() as f64);
-                    game.borrow_mut().create_capture_ball(evt.offset_x() as f64,evt.offset_y() as f64);
-
-                },
-                GameResult::Lost => {
-                    game.borrow_mut().restart();
-                },
-                GameResult::Won => {
-                    game.borrow_mut().next_level();
+        if clicked && !is_playing_back() {
+            let input = match game_result {
+                GameResult::Playing => match pointer_to_game(&evt, &game.borrow()) {
+                    Some(point) => Input::Click { x: point.x, y: point.y },
+                    // clicks next to the arena don't shoot
+                    None => return,
                 },
-        }
+                GameResult::Lost => Input::Change(ChangeState::RestartLevel),
+                GameResult::Won => Input::Change(ChangeState::NextLevel),
+            };
+            game.borrow_mut().apply_input(input);
       }}
     )) as Box<dyn FnMut(MouseEvent)>);
 
+    // mouse position over the canvas, for the capture preview
+    static HOVER: RefCell<Option<Point>> = const { RefCell::new(None) };
+
+    static HANDLE_MOUSEMOVE: Closure<dyn FnMut(MouseEvent)> =
+    Closure::wrap(Box::new(|evt: MouseEvent| HOVER.with(|hover| {
+        *hover.borrow_mut() = GAME.with(|game| pointer_to_game(&evt, &game.borrow()));
+    })) as Box<dyn FnMut(MouseEvent)>);
+
+    static HANDLE_MOUSELEAVE: Closure<dyn FnMut(MouseEvent)> =
+    Closure::wrap(Box::new(|_evt: MouseEvent| HOVER.with(|hover| {
+        *hover.borrow_mut() = None;
+    })) as Box<dyn FnMut(MouseEvent)>);
+
+    // also fires when the page is zoomed or moved to a screen with another pixel ratio
+    static HANDLE_RESIZE: Closure<dyn FnMut(Event)> =
+    Closure::wrap(Box::new(|_evt: Event| GAME.with(|game| {
+        fit_canvas(&game.borrow());
+    })) as Box<dyn FnMut(Event)>);
+
+    static PLAYBACK: RefCell<Option<Playback>> = const { RefCell::new(None) };
+
+    // Last capture preview, what it was simulated for and on which frame. Balls keep moving
+    // while the mouse rests, the preview only follows them after the next shot or move.
+    static PREVIEW: RefCell<Option<(PreviewKey, usize, Vec<usize>)>> = const { RefCell::new(None) };
+
+    // best shot for the paused game, shown until the next click or key
+    static HINT: RefCell<Option<Shot>> = const { RefCell::new(None) };
+}
+
+fn is_playing_back() -> bool {
+    PLAYBACK.with(|playback| playback.borrow().is_some())
+}
+
+// Pauses the game on the frame the hint was found for, so it stays right. Frame ids
+// don't advance while paused, so this doesn't go through `apply_input` and replays
+// still line up.
+fn show_hint(game: &mut GameState) {
+    if game.is_paused || game.check_win_lose() != GameResult::Playing {
+        return;
+    }
+
+    if let Some(shot) = solve(game, &SolverConfig::hint()) {
+        game.is_paused = true;
+        HINT.with(|hint| *hint.borrow_mut() = Some(shot));
+    }
+}
+
+// resumes the game if a hint was showing
+fn hide_hint(game: &mut GameState) -> bool {
+    let shown = HINT.with(|hint| hint.borrow_mut().take()).is_some();
+    if shown {
+        game.is_paused = false;
+    }
+    shown
+}
+
+// replay of everything played since the page loaded, call from the JS console for bug reports
+#[wasm_bindgen]
+pub fn export_replay() -> String {
+    GAME.with(|game| game.borrow().replay.to_string())
+}
+
+// the current frame as an SVG document, optionally with the broad phase cells
+#[wasm_bindgen]
+pub fn export_svg(quad_tree: bool) -> String {
+    GAME.with(|game| Renderer::new(&game.borrow()).to_svg(quad_tree))
+}
+
+// starts a new game with a campaign from a JSON level pack, see `levels::LevelPack`
+#[wasm_bindgen]
+pub fn load_level_pack(json: &str) -> Result<(), JsValue> {
+    let pack = LevelPack::from_json(json).map_err(|err| JsValue::from_str(&err.to_string()))?;
+
+    GAME.with(|game| {
+        *game.borrow_mut() = GameState::from_level_pack(&pack, Random::random_seed());
+        fit_canvas(&game.borrow());
+    });
+    HINT.with(|hint| *hint.borrow_mut() = None);
+    PREVIEW.with(|preview| *preview.borrow_mut() = None);
+    PLAYBACK.with(|current| *current.borrow_mut() = None);
+    Ok(())
+}
+
+// restarts the game from the replay's seed and plays the recording back, ignoring user input
+#[wasm_bindgen]
+pub fn play_replay(text: &str) -> Result<(), JsValue> {
+    let replay: Replay = text
+        .parse()
+        .map_err(|err: ReplayError| JsValue::from_str(&err.to_string()))?;
+    let playback = Playback::new(replay);
+
+    GAME.with(|game| {
+        *game.borrow_mut() = playback.new_game();
+        fit_canvas(&game.borrow());
+    });
+    HINT.with(|hint| *hint.borrow_mut() = None);
+    PREVIEW.with(|preview| *preview.borrow_mut() = None);
+    PLAYBACK.with(|current| *current.borrow_mut() = Some(playback));
+    Ok(())
+}
+
+// starts the same levels over in an arena of the given size, the canvas is resized to match
+#[wasm_bindgen]
+pub fn resize_arena(width: usize, height: usize) -> Result<(), JsValue> {
+    if width == 0 || height == 0 {
+        return Err(JsValue::from_str(&format!(
+            "arena {}x{} should not be empty",
+            width, height
+        )));
+    }
+
+    GAME.with(|game| {
+        let levels = game.borrow().all_levels.clone();
+        *game.borrow_mut() = GameState::with_levels(levels, width, height, Random::random_seed());
+        fit_canvas(&game.borrow());
+    });
+    HINT.with(|hint| *hint.borrow_mut() = None);
+    PREVIEW.with(|preview| *preview.borrow_mut() = None);
+    PLAYBACK.with(|current| *current.borrow_mut() = None);
+    Ok(())
+}
+
+// Balls a click at `point` would capture, simulated again only once the mouse moved, a shot
+// was fired or a level started. Frame ids go back to 0 when a level starts or restarts.
+fn preview(game: &GameState, point: Point) -> Vec<usize> {
+    let key = (game.level_id, game.shots, point);
+    PREVIEW.with(|preview| {
+        let mut preview = preview.borrow_mut();
+        match preview.as_ref() {
+            Some((cached, frame_id, captured)) if *cached == key && *frame_id <= game.frame_id => {
+                captured.clone()
+            }
+            _ => {
+                let captured = captured_by_click(game, point, PREVIEW_HORIZON);
+                *preview = Some((key, game.frame_id, captured.clone()));
+                captured
+            }
+        }
+    })
+}
+
+// Sizes the canvas to fill its container with the arena's aspect ratio, at one backing pixel
+// per device pixel, and scales the context so the game keeps drawing in arena units.
+fn fit_canvas(game: &GameState) {
+    let canvas = get_canvas();
+    // the container holds the canvas border too
+    let border_width = (canvas.offset_width() - canvas.client_width()) as f64;
+    let border_height = (canvas.offset_height() - canvas.client_height()) as f64;
+    let (container_width, container_height) = match canvas.parent_element() {
+        Some(parent) if parent.client_width() > 0 => (
+            parent.client_width() as f64 - border_width,
+            parent.client_height() as f64 - border_height,
+        ),
+        _ => (game.rect.w, game.rect.h),
+    };
+    let viewport = Viewport::fit(
+        game.rect,
+        container_width,
+        container_height,
+        window().device_pixel_ratio(),
+    );
+
+    let (width, height) = viewport.backing_size();
+    canvas.set_width(width);
+    canvas.set_height(height);
+    let style = canvas.style();
+    style
+        .set_property("width", &format!("{}px", viewport.css_width))
+        .unwrap_throw();
+    style
+        .set_property("height", &format!("{}px", viewport.css_height))
+        .unwrap_throw();
+
+    // resizing the canvas reset the transform
+    let (scale_x, scale_y) = viewport.scale();
+    get_context()
+        .set_transform(
+            scale_x,
+            0.0,
+            0.0,
+            scale_y,
+            -game.rect.x * scale_x,
+            -game.rect.y * scale_y,
+        )
+        .unwrap_throw();
+}
+
+// Pointer position in arena units, `None` outside the arena. Uses the size the canvas is
+// displayed at right now, so page styles that stretch it are accounted for.
+fn pointer_to_game(evt: &MouseEvent, game: &GameState) -> Option<Point> {
+    let canvas = get_canvas();
+    let bounds = canvas.get_bounding_client_rect();
+    // the content starts inside the canvas border
+    let x = evt.client_x() as f64 - bounds.left() - canvas.client_left() as f64;
+    let y = evt.client_y() as f64 - bounds.top() - canvas.client_top() as f64;
+    let viewport = Viewport::new(
+        game.rect,
+        canvas.client_width() as f64,
+        canvas.client_height() as f64,
+        window().device_pixel_ratio(),
+    );
+    let point = viewport.to_game(x, y);
+    if viewport.contains(point) {
+        Some(point)
+    } else {
+        None
+    }
 }
 
 #[wasm_bindgen(start)]
 pub fn run() -> Result<(), JsValue> {
+    set_panic_hook();
     //let this_window = window();
     HANDLE_KEYDOWN.with(|handle_keydown| {
         window()
@@ -121,16 +352,43 @@ pub fn run() -> Result<(), JsValue> {
             .unwrap_throw();
     });
 
+    HANDLE_RESIZE.with(|handle_resize| {
+        window()
+            .add_event_listener_with_callback(
+                "resize",
+                handle_resize.as_ref().dyn_ref::<Function>().unwrap_throw(),
+            )
+            .unwrap_throw();
+    });
+    GAME.with(|game| fit_canvas(&game.borrow()));
+
+    let canvas = get_canvas();
+    HANDLE_MOUSEMOVE.with(|handle_mousemove| {
+        canvas
+            .add_event_listener_with_callback(
+                "mousemove",
+                handle_mousemove.as_ref().dyn_ref::<Function>().unwrap_throw(),
+            )
+            .unwrap_throw();
+    });
+
+    HANDLE_MOUSELEAVE.with(|handle_mouseleave| {
+        canvas
+            .add_event_listener_with_callback(
+                "mouseleave",
+                handle_mouseleave.as_ref().dyn_ref::<Function>().unwrap_throw(),
+            )
+            .unwrap_throw();
+    });
+
     let f = Rc::new(RefCell::new(None));
     let g = f.clone();
     let mut ctx = get_context();
     //let debug_ctx = get_debug_context();
-    let renderer = Renderer::new(GAME.into());
+    let mut clock = FixedStep::new();
 
-    *g.borrow_mut() = Some(Closure::wrap(Box::new(move || {
+    *g.borrow_mut() = Some(Closure::wrap(Box::new(move |now: f64| {
         GAME.with(|game| {
-            let mut doc = document();
-
             let root_container = document()
                 .get_element_by_id("score")
                 .unwrap_throw()
@@ -138,7 +396,7 @@ pub fn run() -> Result<(), JsValue> {
                 .unwrap_throw();
 
             let mut game = game.borrow_mut();
-            root_container.set_inner_html(&*game.get_stats());
+            root_container.set_inner_html(&game.get_stats());
 
             let goal_container = document()
                 .get_element_by_id("goal")
@@ -146,28 +404,61 @@ pub fn run() -> Result<(), JsValue> {
                 .dyn_into::<HtmlElement>()
                 .unwrap_throw();
 
-            goal_container.set_inner_html(&*game.get_goal());
+            goal_container.set_inner_html(&game.get_goal());
 
+            // as many ticks as fit into the time since the last display frame
+            for _ in 0..clock.advance(now) {
+                PLAYBACK.with(|playback| {
+                    let mut playback = playback.borrow_mut();
+                    match playback.as_mut() {
+                        Some(current) => {
+                            current.step(&mut game);
+                            // hand control back to the player once the recording runs out
+                            if current.is_finished() {
+                                *playback = None;
+                            }
+                        }
+                        None => game.advance(),
+                    }
+                });
+            }
+
+            // a paused game doesn't move, so don't draw it between ticks
+            let interpolation = if game.is_paused { 1.0 } else { clock.alpha() };
+            let renderer = Renderer::interpolated(&game, interpolation);
             match game.result {
                 GameResult::Playing => {
                     if !game.is_paused {
-                        game.tick();
-                        game.render_state(&mut ctx, true);
+                        renderer.render_state(&mut ctx, true);
+
+                        let hover = HOVER.with(|hover| *hover.borrow());
+                        if let Some(point) = hover {
+                            if game.shots > 0 && !is_playing_back() {
+                                let captured = preview(&game, point);
+                                renderer.render_preview(&mut ctx, point, &captured);
+                            }
+                        }
                     }
+                    HINT.with(|hint| {
+                        if let Some(shot) = hint.borrow().as_ref() {
+                            renderer.render_state(&mut ctx, true);
+                            renderer.render_hint(&mut ctx, shot);
+                        }
+                    });
                 }
                 GameResult::Lost => {
                     console::log_1(&format!("result: {:#?}", game.result).into());
-                    game.render_lost(&mut ctx);
+                    renderer.render_lost(&mut ctx);
                 }
                 GameResult::Won => {
                     console::log_1(&format!("result: {:#?}", game.result).into());
-                    game.render_won(&mut ctx);
+                    renderer.render_won(&mut ctx);
                 }
             }
         });
 
         request_animation_frame(f.borrow().as_ref().unwrap());
-    }) as Box<dyn FnMut()>));
+    }) as Box<dyn FnMut(f64)>));
 
     request_animation_frame(g.borrow().as_ref().unwrap());
     Ok(())
@@ -1,29 +1,17 @@
 use crate::ball::BallState::{Expanding, Normal, Shrinking, Vanish};
-use crate::ball::{balls_distance_squared, Ball, BallPair, BallPairIds, BallType, ACTIVE_BALL};
+use crate::ball::{
+    calc_moment_of_collision, resolve_collision, Ball, BallPair, BallPairIds, ACTIVE_BALL,
+};
+use crate::broadphase::{BroadPhase, BroadPhaseKind};
 use crate::geometry::{Point, Rect, RenderingRect};
-use crate::quadtree::QuadTreeNode;
-use std::borrow::BorrowMut;
-use std::collections::{HashMap, HashSet};
-use web_sys::console;
+use crate::levels::{Level, LevelPack};
+use crate::observation::{Encoded, Encoding};
+use crate::random::Random;
+use crate::replay::{Input, Replay};
+use itertools::Itertools;
+use std::collections::HashMap;
 
 #[derive(Debug, Clone)]
-pub struct Level {
-    pub max_shots: usize,
-    pub num_of_balls: usize,
-    pub num_captured: usize,
-}
-
-impl Level {
-    fn new(max_shots: usize, num_of_balls: usize, num_captured: usize) -> Self {
-        Self {
-            max_shots,
-            num_of_balls,
-            num_captured,
-        }
-    }
-}
-
-#[derive(Debug)]
 pub struct GameState {
     pub all_levels: Vec<Level>,
     pub captured: usize,
@@ -36,8 +24,12 @@ pub struct GameState {
     pub rect: Rect,
     pub result: GameResult,
     pub shots: usize,
-    pub tree: Option<Box<QuadTreeNode>>,
+    pub broad_phase: Box<dyn BroadPhase>,
     pub frame_id: usize,
+    pub seed: u64,
+    pub rng: Random,
+    pub replay: Replay,
+    pub mode: GameMode,
 }
 
 impl GameState {
@@ -53,105 +45,167 @@ impl GameState {
     }
 
     pub fn new(width: usize, height: usize) -> Self {
-        let all_levels: Vec<Level> = vec![
-            Level::new(1, 5, 1),
-            Level::new(1, 5, 2),
-            Level::new(1, 8, 3),
-            Level::new(1, 10, 5),
-            Level::new(1, 15, 7),
-            Level::new(1, 20, 9),
-            Level::new(1, 20, 12),
-            Level::new(1, 25, 15),
-            Level::new(1, 30, 20),
-            Level::new(1, 30, 22),
-            Level::new(1, 35, 27),
-            Level::new(1, 40, 31),
-            Level::new(1, 45, 40),
-            Level::new(1, 55, 47),
-            Level::new(1, 60, 50),
-            Level::new(1, 65, 57),
-            Level::new(1, 65, 60),
-            Level::new(1, 90, 80),
-            Level::new(1, 99, 97),
-        ];
+        Self::with_seed(width, height, Random::random_seed())
+    }
+
+    pub fn with_seed(width: usize, height: usize, seed: u64) -> Self {
+        Self::with_levels(LevelPack::classic().levels, width, height, seed)
+    }
+
+    // arena size and levels come from the pack
+    pub fn from_level_pack(pack: &LevelPack, seed: u64) -> Self {
+        Self::with_levels(pack.levels.clone(), pack.width, pack.height, seed)
+    }
+
+    pub fn with_levels(all_levels: Vec<Level>, width: usize, height: usize, seed: u64) -> Self {
+        let rect = Rect {
+            x: 0.0,
+            y: 0.0,
+            w: width as f64,
+            h: height as f64,
+        };
 
         let mut new_state = Self {
-            rect: Rect {
-                x: 0.0,
-                y: 0.0,
-                w: width as f64,
-                h: height as f64,
-            },
+            rect,
             objects: HashMap::new(),
             next_id: 1,
             captured: 0,
             captured_required: 0,
             shots: 3,
             result: GameResult::Playing,
-            tree: None,
+            broad_phase: BroadPhaseKind::QuadTree.create(rect),
             is_paused: false,
             is_render_debug: false,
-            all_levels,
             level_id: 0,
             frame_id: 0,
+            seed,
+            rng: Random::new(seed),
+            replay: Replay::with_levels(seed, width, height, all_levels.clone()),
+            all_levels,
+            mode: GameMode::default(),
         };
 
         new_state.next_level();
 
-        let rect = Rect {
-            x: 0.0,
-            y: 0.0,
-            w: width as f64,
-            h: height as f64,
-        };
-        let qt = QuadTreeNode::new(rect, 0, 0);
-        new_state.tree = qt;
-
         new_state
     }
 
+    // after the last level the campaign starts over from the first one
     pub fn next_level(&mut self) {
-        let next_level_id: usize = self.level_id + 1;
-        let level: Level = self.all_levels[next_level_id].clone();
-
-        self.captured = 0;
-        self.captured_required = level.num_captured;
-        self.shots = level.max_shots;
-        self.result = GameResult::Playing;
-        self.frame_id = 0;
-        while self.objects.len() < level.num_of_balls {
-            self.insert_object(&mut Ball::random_ball(
-                0,
-                self.rect.w as usize,
-                self.rect.h as usize,
-                BallType::random_ball_type(),
-            ));
+        if self.is_last_level() {
+            self.start_level(1);
+        } else {
+            self.start_level(self.level_id + 1);
         }
+    }
 
-        self.level_id = next_level_id;
+    pub fn is_last_level(&self) -> bool {
+        self.level_id >= self.all_levels.len()
     }
 
     pub fn restart(&mut self) {
-        let level: Level = self.all_levels[self.level_id].clone();
+        self.start_level(self.level_id);
+    }
+
+    // level ids start at 1, 0 means no level was started yet
+    pub fn level(&self) -> &Level {
+        &self.all_levels[self.level_id - 1]
+    }
 
+    // spawns a fresh layout, which only depends on the seed and the level id
+    pub fn start_level(&mut self, level_id: usize) {
+        assert!(
+            (1..=self.all_levels.len()).contains(&level_id),
+            "level {} doesn't exist, there are {} levels",
+            level_id,
+            self.all_levels.len()
+        );
+        let level: Level = self.all_levels[level_id - 1].clone();
+
+        self.level_id = level_id;
         self.frame_id = 0;
         self.captured = 0;
         self.captured_required = level.num_captured;
         self.shots = level.max_shots;
         self.result = GameResult::Playing;
+        self.objects.clear();
+        self.next_id = 1;
+        self.rng = Random::for_level(self.seed, level_id);
 
         while self.objects.len() < level.num_of_balls {
-            self.insert_object(&mut Ball::random_ball(
+            let ball_type = level.random_ball_type(&mut self.rng);
+            let mut ball = Ball::random_ball(
+                &mut self.rng,
                 0,
                 self.rect.w as usize,
                 self.rect.h as usize,
-                BallType::random_ball_type(),
-            ));
+                ball_type,
+            );
+            self.insert_object(&mut ball);
+        }
+    }
+
+    // Copy to simulate on, like `clone` but without copying the recorded inputs, which
+    // grow all game long, or the broad phase, which is built fresh of the given kind.
+    pub fn sandbox(&self, kind: BroadPhaseKind) -> Self {
+        Self {
+            all_levels: self.all_levels.clone(),
+            captured: self.captured,
+            captured_required: self.captured_required,
+            is_paused: self.is_paused,
+            is_render_debug: self.is_render_debug,
+            level_id: self.level_id,
+            next_id: self.next_id,
+            objects: self.objects.clone(),
+            rect: self.rect,
+            result: self.result,
+            shots: self.shots,
+            broad_phase: kind.create(self.rect),
+            frame_id: self.frame_id,
+            seed: self.seed,
+            rng: self.rng.clone(),
+            // only what happens in the sandbox, without levels, it's never played back
+            replay: Replay::with_levels(
+                self.seed,
+                self.rect.w as usize,
+                self.rect.h as usize,
+                vec![],
+            ),
+            mode: self.mode,
         }
     }
 
+    // swaps the collision candidate search, the balls are picked up on the next tick
+    pub fn use_broad_phase(&mut self, kind: BroadPhaseKind) {
+        self.broad_phase = kind.create(self.rect);
+    }
+
     pub fn quit(&self) {}
 
+    // single entry point for player input, every input is recorded into `replay`
+    pub fn apply_input(&mut self, input: Input) {
+        if input == Input::Change(ChangeState::NoChange) {
+            return;
+        }
+        self.replay.record(self.frame_id, input);
+
+        match input {
+            Input::Click { x, y } => self.create_capture_ball(x, y),
+            Input::Change(ChangeState::PlayPause) => self.pause_play(),
+            Input::Change(ChangeState::NextLevel) => self.next_level(),
+            Input::Change(ChangeState::RestartLevel) => self.restart(),
+            Input::Change(ChangeState::Quit) => self.quit(),
+            Input::Change(ChangeState::NoChange) => {}
+        }
+    }
+
+    // called once per animation frame, only ticks while the level is running
+    pub fn advance(&mut self) {
+        if self.result == GameResult::Playing && !self.is_paused {
+            self.tick();
+        }
+    }
+
     pub fn get_stats(&self) -> String {
         format!(
             "CAPTURED: {} / {}, SHOTS LEFT: {}",
@@ -183,11 +237,30 @@ impl GameState {
     }
 
     pub fn get_rectangles(&self) -> Vec<RenderingRect> {
-        if self.tree.is_none() {
-            return vec![];
-        }
+        self.broad_phase.rectangles()
+    }
 
-        self.tree.as_ref().unwrap().get_rectangles()
+    // the game as model input, see `observation::Encoding`
+    pub fn observe(&self, encoding: Encoding) -> Encoded {
+        encoding.encode(self)
+    }
+
+    pub fn info_balls(&self) -> Vec<String> {
+        self.objects
+            .keys()
+            .sorted()
+            .map(|id| {
+                let ball = &self.objects[id];
+                format!(
+                    "{} {} {} {} {}",
+                    ball.ball_type,
+                    ball.pos.x,
+                    ball.pos.y,
+                    ball.next_position.x,
+                    ball.next_position.y
+                )
+            })
+            .collect()
     }
 
     pub fn pause_play(&mut self) {
@@ -199,28 +272,31 @@ impl GameState {
     }
 
     fn handle_collisions(&mut self) {
-        if self.tree.is_none() {
-            return;
-        }
-
         // find collisions candidates
-
-        let mut candidates: HashSet<BallPairIds> = HashSet::new();
-        for ball in self.objects.values() {
-            let found = self.tree.as_ref().expect("").search(ball.id);
-            for f in found {
-                candidates.insert(f);
-            }
-        }
+        self.broad_phase.update(self.rect, &self.objects);
+
+        // bounces are resolved one pair after another, so visit pairs in a fixed order,
+        // which doesn't depend on the broad phase either
+        let mut candidates: Vec<BallPairIds> = self
+            .broad_phase
+            .candidates()
+            .into_iter()
+            .map(|pair| BallPairIds {
+                first: pair.first.min(pair.second),
+                second: pair.first.max(pair.second),
+            })
+            .collect();
+        candidates.sort_by_key(|pair| (pair.first, pair.second));
+        candidates.dedup();
 
         // check collisions
         for cand in candidates {
-            let mut first_ball_state = self
+            let first_ball_state = self
                 .objects
-                .get_mut(&cand.first)
+                .get(&cand.first)
                 .expect("this ball should exist")
                 .ball_state;
-            let mut second_ball_state = self
+            let second_ball_state = self
                 .objects
                 .get(&cand.second)
                 .expect("this ball should exist")
@@ -238,14 +314,20 @@ impl GameState {
                 first: *ball1,
                 second: *ball2,
             };
-            let calc_dist = balls_distance_squared(*ball1, *ball2);
-            let my_dist = ball1.radius * ball1.radius + ball2.radius * ball2.radius;
 
-            if ball_pair.is_collision_bb() {
+            let is_collision = match self.mode.narrow_phase {
+                NarrowPhase::BoundingBox => ball_pair.is_collision_bb(),
+                NarrowPhase::Circle => ball_pair.is_collision(),
+                NarrowPhase::Swept => {
+                    ball_pair.is_collision() || calc_moment_of_collision(ball1, ball2).is_some()
+                }
+            };
+
+            if is_collision {
                 if (first_ball_state == Shrinking || first_ball_state == Expanding)
                     && second_ball_state == Normal
                 {
-                    let mut second_ball = self
+                    let second_ball = self
                         .objects
                         .get_mut(&cand.second)
                         .expect("this ball should exist");
@@ -257,59 +339,51 @@ impl GameState {
                 if (second_ball_state == Shrinking || second_ball_state == Expanding)
                     && first_ball_state == Normal
                 {
-                    let mut first_ball = self
+                    let first_ball = self
                         .objects
                         .get_mut(&cand.first)
                         .expect("this ball should exist");
                     first_ball.next_ball_state = Expanding;
                     first_ball.set_captured();
                 }
+
+                if self.mode.bouncing && first_ball_state == Normal && second_ball_state == Normal {
+                    let resolved = resolve_collision(ball_pair);
+                    for ball in [resolved.first, resolved.second] {
+                        self.objects
+                            .get_mut(&ball.id)
+                            .expect("this ball should exist")
+                            .next_velocity = ball.next_velocity;
+                    }
+                }
             }
         }
     }
 
     fn get_number_of_vanished_balls(&self) -> usize {
-        self.all_levels[self.level_id].num_of_balls - self.objects.len()
-            + self.all_levels[self.level_id].max_shots
-            - self.shots
+        // active balls are in `objects` too, so add the shots before subtracting
+        self.level().num_of_balls + self.level().max_shots - self.shots - self.objects.len()
     }
 
     pub fn tick(&mut self) {
-        let num_objects = &self.objects.len();
         self.frame_id += 1;
         self.objects.retain(|_key, obj| obj.ball_state != Vanish);
         self.captured = self.get_number_of_vanished_balls()
             + self.objects.values().filter(|obj| obj.is_captured).count();
 
         self.result = self.check_win_lose();
-        for (_, obj) in &mut self.objects {
-            obj.tick();
+        let arena = self.rect;
+        for obj in self.objects.values_mut() {
+            obj.tick(arena);
         }
 
-        self.update_quadtree();
         self.handle_collisions();
 
-        for (_, obj) in &mut self.objects {
+        for obj in self.objects.values_mut() {
             obj.apply_tick_changes();
         }
     }
 
-    fn update_quadtree(&mut self) {
-        let rect = self.rect;
-        let mut qt = QuadTreeNode::new(rect, 0, 0);
-
-        for ball in self.objects.clone().values() {
-            qt.as_deref_mut().expect("").insert_ball(ball);
-        }
-        self.tree = qt;
-    }
-
-    pub fn show_state(&self) {
-        for (_, obj) in &self.objects {
-            console::log_1(&format!("{} {}", obj.pos.x, obj.pos.y).into());
-        }
-    }
-
     pub fn create_capture_ball(&mut self, x: f64, y: f64) {
         if self.shots == 0 {
             return;
@@ -318,7 +392,6 @@ impl GameState {
             return;
         }
 
-        console::log_1(&format!("{} {}", x, y).into());
         let mut active_ball = Ball::new(
             self.gen_next_id(),
             Point { x, y },
@@ -327,7 +400,7 @@ impl GameState {
             Expanding,
         );
         active_ball.is_captured = true;
-        self.insert_object(active_ball.borrow_mut());
+        self.insert_object(&mut active_ball);
         self.shots -= 1;
     }
 }
@@ -347,3 +420,30 @@ pub enum GameResult {
     Lost,
     Won,
 }
+
+// how overlapping balls are detected once the broad phase found them as candidates
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NarrowPhase {
+    // axis-aligned bounding boxes, balls touching a box corner count as captured
+    BoundingBox,
+    // exact circle-circle distance
+    Circle,
+    // circles plus time of impact during the tick, so fast balls can't tunnel through
+    Swept,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GameMode {
+    pub narrow_phase: NarrowPhase,
+    // normal balls bounce off each other instead of passing through
+    pub bouncing: bool,
+}
+
+impl Default for GameMode {
+    fn default() -> Self {
+        Self {
+            narrow_phase: NarrowPhase::Swept,
+            bouncing: false,
+        }
+    }
+}
@@ -0,0 +1,164 @@
+use crate::ball::{Ball, BallState};
+use crate::geometry::{Point, Rect};
+use crate::logic::GameState;
+
+// Bump whenever any encoding below changes its layout or meaning, so models trained on
+// older observations can tell they no longer fit. The layouts are described in the book,
+// chapter "Machine Learning Player".
+pub const ENCODING_VERSION: u32 = 1;
+
+// present, x, y, velocity x, velocity y, radius, state, type
+pub const BALL_FEATURES: usize = 8;
+
+// raster channels: normal balls and active (expanding or shrinking) balls
+pub const RASTER_CHANNELS: usize = 2;
+
+// ball type index used in the encodings, part of version 1, so only append to it
+pub const BALL_TYPE_NAMES: [&str; 19] = [
+    "WHITE_BALL",
+    "ORANGE_BALL",
+    "BROWN_BALL",
+    "NAVY_BALL",
+    "PURPLE_BALL",
+    "GRAY_BALL",
+    "YELLOW_BALL",
+    "RED_BALL",
+    "CYAN_BALL",
+    "MAGENTA_BALL",
+    "TEAL_BALL",
+    "BLUE_BALL",
+    "GREEN_BALL",
+    "OLIVE_BALL",
+    "MAROON_BALL",
+    "FORREST_BALL",
+    "SILVER_BALL",
+    "BLACK_BALL",
+    "ACTIVE_BALL",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    // `count` balls closest to the arena center, padded with zeros when there are fewer
+    Nearest { count: usize },
+    // occupancy grid over the arena, `cols` x `rows` cells per channel, at least one of each
+    Raster { cols: usize, rows: usize },
+    // every ball, ordered by id, so the length changes with the number of balls
+    Raw,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Encoded {
+    pub version: u32,
+    // dimensions of `data`, outermost first
+    pub shape: Vec<usize>,
+    pub data: Vec<f32>,
+}
+
+impl Encoding {
+    pub fn encode(&self, game: &GameState) -> Encoded {
+        let mut balls: Vec<&Ball> = game.objects.values().collect();
+        balls.sort_by_key(|ball| ball.id);
+
+        let (shape, data) = match *self {
+            Encoding::Nearest { count } => {
+                let center = game.rect.center();
+                balls.sort_by(|a, b| {
+                    distance_squared(a.pos, center).total_cmp(&distance_squared(b.pos, center))
+                });
+
+                let mut data = vec![0.0; count * BALL_FEATURES];
+                for (ball, features) in balls.iter().zip(data.chunks_mut(BALL_FEATURES)) {
+                    features.copy_from_slice(&ball_features(ball, game.rect));
+                }
+                (vec![count, BALL_FEATURES], data)
+            }
+            Encoding::Raster { cols, rows } => {
+                // an empty grid has no cell to put a ball in
+                let (cols, rows) = (cols.max(1), rows.max(1));
+                (
+                    vec![RASTER_CHANNELS, rows, cols],
+                    raster(&balls, game.rect, cols, rows),
+                )
+            }
+            Encoding::Raw => {
+                let data = balls
+                    .iter()
+                    .flat_map(|ball| ball_features(ball, game.rect))
+                    .collect();
+                (vec![balls.len(), BALL_FEATURES], data)
+            }
+        };
+
+        Encoded {
+            version: ENCODING_VERSION,
+            shape,
+            data,
+        }
+    }
+}
+
+fn distance_squared(a: Point, b: Point) -> f64 {
+    (a.x - b.x) * (a.x - b.x) + (a.y - b.y) * (a.y - b.y)
+}
+
+pub fn ball_type_index(name: &str) -> Option<usize> {
+    BALL_TYPE_NAMES.iter().position(|known| *known == name)
+}
+
+pub fn ball_state_index(state: BallState) -> usize {
+    match state {
+        BallState::Normal => 0,
+        BallState::Expanding => 1,
+        BallState::Shrinking => 2,
+        BallState::Vanish => 3,
+    }
+}
+
+// position relative to the arena, velocity in pixels per tick and radius in pixels
+fn ball_features(ball: &Ball, arena: Rect) -> [f32; BALL_FEATURES] {
+    let step = ball.step();
+    [
+        1.0,
+        ((ball.pos.x - arena.x) / arena.w) as f32,
+        ((ball.pos.y - arena.y) / arena.h) as f32,
+        step.x as f32,
+        step.y as f32,
+        ball.radius as f32,
+        ball_state_index(ball.ball_state) as f32,
+        ball_type_index(ball.ball_type).map_or(-1.0, |index| index as f32),
+    ]
+}
+
+// a cell is occupied when a ball overlaps any part of it
+fn raster(balls: &[&Ball], arena: Rect, cols: usize, rows: usize) -> Vec<f32> {
+    let mut data = vec![0.0; RASTER_CHANNELS * rows * cols];
+    let cell_w = arena.w / cols as f64;
+    let cell_h = arena.h / rows as f64;
+
+    for ball in balls {
+        let channel = match ball.ball_state {
+            BallState::Normal => 0,
+            BallState::Expanding | BallState::Shrinking => 1,
+            BallState::Vanish => continue,
+        };
+
+        let to_col = |x: f64| (((x - arena.x) / cell_w).floor().max(0.0) as usize).min(cols - 1);
+        let to_row = |y: f64| (((y - arena.y) / cell_h).floor().max(0.0) as usize).min(rows - 1);
+
+        for row in to_row(ball.pos.y - ball.radius)..=to_row(ball.pos.y + ball.radius) {
+            for col in to_col(ball.pos.x - ball.radius)..=to_col(ball.pos.x + ball.radius) {
+                let x0 = arena.x + col as f64 * cell_w;
+                let y0 = arena.y + row as f64 * cell_h;
+                let closest = Point {
+                    x: ball.pos.x.clamp(x0, x0 + cell_w),
+                    y: ball.pos.y.clamp(y0, y0 + cell_h),
+                };
+                if distance_squared(closest, ball.pos) <= ball.radius * ball.radius {
+                    data[(channel * rows + row) * cols + col] = 1.0;
+                }
+            }
+        }
+    }
+
+    data
+}
@@ -0,0 +1,137 @@
+use crate::ball::Ball;
+use crate::ball::BallState::{Expanding, Normal, Shrinking};
+use crate::constants::FULL;
+use crate::geometry::Point;
+use crate::logic::{GameResult, GameState};
+use crate::random::Random;
+use crate::replay::Input;
+
+// Something that plays the game: it looks at the state every tick and may click.
+pub trait Player {
+    fn name(&self) -> &str;
+
+    // called before every tick while the level is running, a returned point is clicked
+    fn act(&mut self, game: &GameState) -> Option<Point>;
+}
+
+// clicks anywhere in the arena at random moments
+#[derive(Debug, Clone)]
+pub struct RandomBot {
+    rng: Random,
+    // probability of clicking on a tick
+    chance: f64,
+}
+
+impl RandomBot {
+    pub fn new(seed: u64) -> Self {
+        Self::with_chance(seed, 1.0 / 60.0)
+    }
+
+    pub fn with_chance(seed: u64, chance: f64) -> Self {
+        Self {
+            rng: Random::new(seed),
+            chance,
+        }
+    }
+}
+
+impl Player for RandomBot {
+    fn name(&self) -> &str {
+        "random"
+    }
+
+    fn act(&mut self, game: &GameState) -> Option<Point> {
+        if game.shots == 0 || !self.rng.chance(self.chance) {
+            return None;
+        }
+
+        Some(Point {
+            x: game.rect.x + self.rng.random_range(0, game.rect.w as usize) as f64,
+            y: game.rect.y + self.rng.random_range(0, game.rect.h as usize) as f64,
+        })
+    }
+}
+
+// Waits until the previous shot has burned out, then clicks the ball position that has the
+// most other balls within the capture ball's full radius.
+#[derive(Debug, Clone, Default)]
+pub struct GreedyBot;
+
+impl GreedyBot {
+    pub fn best_click(game: &GameState) -> Option<(Point, usize)> {
+        let mut balls: Vec<&Ball> = game
+            .objects
+            .values()
+            .filter(|ball| ball.ball_state == Normal)
+            .collect();
+        balls.sort_by_key(|ball| ball.id);
+
+        let mut best: Option<(Point, usize)> = None;
+        for candidate in &balls {
+            let covered = balls
+                .iter()
+                .filter(|ball| {
+                    let dx = ball.pos.x - candidate.pos.x;
+                    let dy = ball.pos.y - candidate.pos.y;
+                    let reach = FULL + ball.radius;
+                    dx * dx + dy * dy <= reach * reach
+                })
+                .count();
+
+            if best.is_none_or(|(_, most)| covered > most) {
+                best = Some((candidate.pos, covered));
+            }
+        }
+        best
+    }
+}
+
+impl Player for GreedyBot {
+    fn name(&self) -> &str {
+        "greedy"
+    }
+
+    fn act(&mut self, game: &GameState) -> Option<Point> {
+        let capturing = game
+            .objects
+            .values()
+            .any(|ball| ball.ball_state == Expanding || ball.ball_state == Shrinking);
+        if game.shots == 0 || capturing {
+            return None;
+        }
+
+        Self::best_click(game).map(|(point, _)| point)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LevelOutcome {
+    pub result: GameResult,
+    pub captured: usize,
+    pub captured_required: usize,
+    pub frames: usize,
+}
+
+// plays one level until it is won or lost, or gives up after `max_frames`
+pub fn play_level(
+    player: &mut dyn Player,
+    game: &mut GameState,
+    max_frames: usize,
+) -> LevelOutcome {
+    while game.result == GameResult::Playing && game.frame_id < max_frames {
+        if let Some(point) = player.act(game) {
+            game.apply_input(Input::Click {
+                x: point.x,
+                y: point.y,
+            });
+        }
+        game.advance();
+    }
+
+    LevelOutcome {
+        result: game.result,
+        captured: game.captured,
+        captured_required: game.captured_required,
+        frames: game.frame_id,
+    }
+}
@@ -1,291 +1,312 @@
-use std::borrow::{Borrow, BorrowMut};
 use std::collections::HashMap;
-use std::iter::FromIterator;
-use web_sys::console;
-use crate::ball::{Ball, BallPair, BallPairIds, is_ball_in_cell, is_ball_in_cell_diag};
-use itertools::{all, any, Itertools};
+use crate::ball::{Ball, BallPairIds};
+use itertools::Itertools;
 use crate::geometry::{Cells, Rect, RenderingRect};
-use crate::logic;
 
-#[derive(Debug)]
+// index of a node in `QuadTree::nodes`
+pub type NodeIndex = usize;
+
+const ROOT: NodeIndex = 0;
+
+#[derive(Debug, Clone)]
 pub struct QuadTreeNode {
-    rect: Rect,
-    id: usize,
-    depth: u8,
-    balls: HashMap<usize, Ball>,
-    top_left: ChildNode,
-    top_right: ChildNode,
-    bottom_left: ChildNode,
-    bottom_right: ChildNode,
+    pub rect: Rect,
+    pub id: usize,
+    pub depth: u8,
+    // ids of every ball inserted through this node, including the ones that moved on to a child
+    pub balls: Vec<usize>,
+    // top left, top right, bottom left, bottom right
+    pub children: [Option<NodeIndex>; 4],
+    pub parent: Option<NodeIndex>,
+    // position in the parent's `children`
+    slot: usize,
 }
 
-pub type ChildNode = Option<Box<QuadTreeNode>>;
-
-impl Clone for QuadTreeNode {
-    fn clone(&self) -> Self {
-        Self {
-            rect: self.rect.clone(),
-            id: self.id,
-            depth: self.depth,
-            balls: self.balls.clone(),
-            top_left: self.top_left.clone(),
-            top_right: self.top_right.clone(),
-            bottom_left: self.bottom_left.clone(),
-            bottom_right: self.bottom_right.clone(),
-        }
-    }
+// Quadtree with all nodes in a single vec, nodes point to their children by index and
+// only keep ball ids. `clear` keeps the nodes around, so rebuilding the tree every
+// frame stops allocating once it has grown to the size of the scene. Balls can also be
+// moved and removed one by one, so the tree can be kept up to date across frames.
+#[derive(Debug, Clone)]
+pub struct QuadTree {
+    nodes: Vec<QuadTreeNode>,
+    // nodes handed out since the last clear, the ones after it are kept for reuse
+    len: usize,
+    // nodes pruned since the last clear, reused before `len` grows
+    free: Vec<NodeIndex>,
+    // deepest node every ball was inserted into
+    deepest: HashMap<usize, NodeIndex>,
+    // compares every broad phase update with a rebuilt tree and panics when they differ, for
+    // tests and the bench harness, it doubles the cost of an update
+    pub check_incremental: bool,
 }
 
-
-impl QuadTreeNode {
-    pub fn new(rect: Rect, id: usize, depth: u8) -> ChildNode {
-        //console::log_1(&format!("QTN {} has {}", id, size).into());
-        let balls: HashMap<usize, Ball> = HashMap::new();
-
-        Option::from(Box::new(Self {
-            rect,
-            id,
-            depth,
-            balls,
-            top_left: None,
-            top_right: None,
-            bottom_left: None,
-            bottom_right: None,
-        }))
+impl QuadTree {
+    pub fn new(rect: Rect) -> Self {
+        let mut tree = Self {
+            nodes: vec![],
+            len: 0,
+            free: vec![],
+            deepest: HashMap::new(),
+            check_incremental: false,
+        };
+        tree.clear(rect);
+        tree
     }
 
-    pub fn new_top_left(&self) -> ChildNode {
-        QuadTreeNode::new(self.rect.top_left(), self.id * 10 + 1, self.depth + 1)
+    // empties the tree, keeping the allocated nodes for the next frame
+    pub fn clear(&mut self, rect: Rect) {
+        self.len = 0;
+        self.free.clear();
+        self.deepest.clear();
+        self.new_node(rect, 0, 0, None, 0);
     }
 
-    pub fn new_top_right(&self) -> ChildNode {
-        QuadTreeNode::new(self.rect.top_right(), self.id * 10 + 2, self.depth + 1)
+    fn new_node(
+        &mut self,
+        rect: Rect,
+        id: usize,
+        depth: u8,
+        parent: Option<NodeIndex>,
+        slot: usize,
+    ) -> NodeIndex {
+        let index = match self.free.pop() {
+            Some(index) => index,
+            None => {
+                self.len += 1;
+                self.len - 1
+            }
+        };
+
+        if index < self.nodes.len() {
+            let node = &mut self.nodes[index];
+            node.rect = rect;
+            node.id = id;
+            node.depth = depth;
+            node.balls.clear();
+            node.children = [None; 4];
+            node.parent = parent;
+            node.slot = slot;
+        } else {
+            self.nodes.push(QuadTreeNode {
+                rect,
+                id,
+                depth,
+                balls: vec![],
+                children: [None; 4],
+                parent,
+                slot,
+            });
+        }
+
+        index
     }
 
-    pub fn new_bottom_left(&self) -> ChildNode {
-        QuadTreeNode::new(self.rect.bottom_left(), self.id * 10 + 3, self.depth + 1)
+    pub fn root(&self) -> &QuadTreeNode {
+        &self.nodes[ROOT]
     }
 
-    pub fn new_bottom_right(&self) -> ChildNode {
-        QuadTreeNode::new(self.rect.bottom_right(), self.id * 10 + 4, self.depth + 1)
+    pub fn node(&self, index: NodeIndex) -> &QuadTreeNode {
+        &self.nodes[index]
     }
 
-    pub fn contains(&self, id: usize) -> bool {
-        self.balls.contains_key(&id)
+    // number of nodes in use
+    pub fn len(&self) -> usize {
+        self.len - self.free.len()
     }
 
-    fn child_node_contains(child: &ChildNode, id: usize) -> bool {
-        if child.is_some() {
-            return child.as_ref().unwrap().contains(id);
-        }
-        false
+    pub fn is_empty(&self) -> bool {
+        self.nodes[ROOT].balls.is_empty()
     }
 
-    pub fn contains_in_children(&self, id: usize) -> bool {
-        if QuadTreeNode::child_node_contains(&self.top_left, id)
-            || QuadTreeNode::child_node_contains(&self.top_right, id)
-            || QuadTreeNode::child_node_contains(&self.bottom_left, id)
-            || QuadTreeNode::child_node_contains(&self.bottom_right, id)
-        {
-            return true;
-        }
-        false
+    pub fn contains(&self, id: usize) -> bool {
+        self.deepest.contains_key(&id)
     }
 
     pub fn insert_ball(&mut self, ball: &Ball) {
-        let node_cell = self.rect.borrow();
-        let new_cells = node_cell.split_to_four_cells();
-        let mut child_node: ChildNode;
-        let ball_box = ball.bounding_rect_current();
-        //console::log_1(&format!("inserting ball {} into {} ball box{:#?} into {:#?}", ball.id, self.id, ball_box, self.rect).into());
-
-        // if !ball_box.fits(self.rect) {
-        //     console::log_1(&format!("dosn't fit, returning inserting ball {} {} {:#?} {:#?}", ball.id, self.id, ball_box, self.rect).into());
-        //     return;
-        // }
-        self.balls.insert(ball.id, *ball);
-
-        // which cell does the ball belong to?
-        match node_cell.where_is_point_relative_to_center(ball.pos) {
-            Cells::TopLeft => {
-                //console::log_1(&format!("top-left -> inserting ball {} {} {:#?}", ball.id, self.id, new_cells.0,).into());
-
-                if !ball_box.fits(new_cells.0) {
-                    //console::log_1(&format!(" doesn't fit into top-left -> inserting ball into parent {} {} {:#?} {:#?} {:#?}", ball.id, self.id, self.rect, ball.pos, new_cells.0, ).into());
-                    return;
-                }
-
-                if self.top_left.is_none() {
-                    self.top_left = self.new_top_left();
-                }
-                self.top_left.as_mut().unwrap().insert_ball(ball);
-            }
-            Cells::TopRight => {
-                if !ball_box.fits(new_cells.1) {
-                    return;
-                }
+        if self.contains(ball.id) {
+            self.move_ball(ball);
+            return;
+        }
 
-                if self.top_right.is_none() {
-                    self.top_right = self.new_top_right();
-                }
-                self.top_right.as_mut().unwrap().insert_ball(ball);
-            }
-            Cells::BottomLeft => {
-                if !ball_box.fits(new_cells.2) {
-                    return;
-                }
+        self.nodes[ROOT].balls.push(ball.id);
+        self.deepest.insert(ball.id, ROOT);
+        self.descend(ROOT, ball);
+    }
 
-                if self.bottom_left.is_none() {
-                    self.bottom_left = self.new_bottom_left();
-                }
-                self.bottom_left.as_mut().unwrap().insert_ball(ball);
+    // Relocates a ball that already is in the tree after it moved or changed size. It climbs
+    // out of cells its bounding rect no longer fits and then goes down as far as it fits,
+    // ending up in the same node a fresh insert would put it in.
+    pub fn move_ball(&mut self, ball: &Ball) {
+        let mut index = match self.deepest.get(&ball.id) {
+            Some(index) => *index,
+            None => {
+                self.insert_ball(ball);
+                return;
             }
-            Cells::BottomRight => {
-                if !ball_box.fits(new_cells.3) {
-                    return;
-                }
+        };
 
-                if self.bottom_right.is_none() {
-                    self.bottom_right = self.new_bottom_right();
-                }
-                self.bottom_right.as_mut().unwrap().insert_ball(ball);
-            }
+        let ball_box = ball.bounding_rect_swept();
+        while index != ROOT && !ball_box.fits_inside(self.nodes[index].rect) {
+            let parent = self.nodes[index].parent.expect("only the root has no parent");
+            self.remove_from_node(index, ball.id);
+            index = parent;
         }
-    }
 
-    pub fn search(&self, id: usize) -> Vec<BallPairIds> {
-        //let mut rez: Vec<BallPairIds> = vec![];
-        let mut pairs: Vec<BallPairIds> = vec![];
+        self.deepest.insert(ball.id, index);
+        self.descend(index, ball);
+    }
 
-        if !self.contains(id) {
-            return vec![];
-        }
-        if !self.contains_in_children(id) {
-            let combos = Vec::from_iter(self.balls.keys().cloned().combinations(2));
-            for c in combos {
-                if c[0] == id || c[1] == id {
-                    pairs.push(BallPairIds { first: c[0], second: c[1] })
-                }
+    pub fn remove_ball(&mut self, id: usize) -> bool {
+        let mut index = match self.deepest.remove(&id) {
+            Some(index) => index,
+            None => return false,
+        };
+
+        loop {
+            let parent = self.nodes[index].parent;
+            self.remove_from_node(index, id);
+            match parent {
+                Some(parent) => index = parent,
+                None => return true,
             }
-            return pairs;
-        }
-
-        if self.top_left.is_some() {
-            let node = self.top_left.as_ref().unwrap();
-            pairs.extend(node.search(id));
-        }
-        if self.top_right.is_some() {
-            let node = self.top_right.as_ref().unwrap();
-            pairs.extend(node.search(id));
-        }
-        if self.bottom_left.is_some() {
-            let node = self.bottom_left.as_ref().unwrap();
-            pairs.extend(node.search(id));
-        }
-        if self.bottom_right.is_some() {
-            let node = self.bottom_right.as_ref().unwrap();
-            pairs.extend(node.search(id));
         }
+    }
 
-        return pairs;
+    // ids of all balls in the tree
+    pub fn ball_ids(&self) -> impl Iterator<Item = usize> + '_ {
+        self.deepest.keys().copied()
     }
 
-    pub fn get_rectangles(&self) -> Vec<RenderingRect> {
-        let x = self.rect.x;
-        let y = self.rect.y;
-        let w = self.rect.w;
-        let h = self.rect.h;
-        let mut many = false;
-        let mut rects = vec![];
+    // takes the ball from `index` down into the children it fits
+    fn descend(&mut self, mut index: NodeIndex, ball: &Ball) {
+        let ball_box = ball.bounding_rect_swept();
+
+        loop {
+            // which cell does the ball belong to?
+            let node_cell = self.nodes[index].rect;
+            let (slot, child_cell) = match node_cell.where_is_point_relative_to_center(ball.pos) {
+                Cells::TopLeft => (0, node_cell.top_left()),
+                Cells::TopRight => (1, node_cell.top_right()),
+                Cells::BottomLeft => (2, node_cell.bottom_left()),
+                Cells::BottomRight => (3, node_cell.bottom_right()),
+            };
+
+            // Candidates only come from a ball's deepest node, so a box touching the split
+            // line has to stay here, where boxes touching it from the other side meet it.
+            if !ball_box.fits_inside(child_cell) {
+                return;
+            }
 
-        if w < 2.0 {
-            return vec![];
-        }
+            index = match self.nodes[index].children[slot] {
+                Some(child) => child,
+                None => {
+                    let id = self.nodes[index].id * 10 + slot + 1;
+                    let depth = self.nodes[index].depth + 1;
+                    let child = self.new_node(child_cell, id, depth, Some(index), slot);
+                    self.nodes[index].children[slot] = Some(child);
+                    child
+                }
+            };
 
-        if self.top_left.is_some() {
-            let tl = self.top_left.as_ref().unwrap();
-            let additional = tl.get_rectangles();
-            rects.extend(additional);
+            self.nodes[index].balls.push(ball.id);
+            self.deepest.insert(ball.id, index);
         }
+    }
 
-        if self.top_right.is_some() {
-            let tl = self.top_right.as_ref().unwrap();
-            let additional = tl.get_rectangles();
-            rects.extend(additional);
+    // removes the id from a single node, dropping the node once it is empty
+    fn remove_from_node(&mut self, index: NodeIndex, id: usize) {
+        let node = &mut self.nodes[index];
+        if let Some(position) = node.balls.iter().position(|ball_id| *ball_id == id) {
+            node.balls.swap_remove(position);
         }
 
-        if self.bottom_left.is_some() {
-            let tl = self.bottom_left.as_ref().unwrap();
-            let additional = tl.get_rectangles();
-            rects.extend(additional);
+        // an empty node has nothing below it either, so it can go
+        if index != ROOT && node.balls.is_empty() {
+            let parent = node.parent.expect("only the root has no parent");
+            let slot = node.slot;
+            self.nodes[parent].children[slot] = None;
+            self.free.push(index);
         }
+    }
 
-        if self.bottom_right.is_some() {
-            let tl = self.bottom_right.as_ref().unwrap();
-            let additional = tl.get_rectangles();
-            rects.extend(additional);
+    // node ids with their sorted ball ids, ordered by node id. Two trees holding the same
+    // balls in the same cells have the same layout, no matter how they were built.
+    pub fn layout(&self) -> Vec<(usize, Vec<usize>)> {
+        let mut layout = vec![];
+        let mut stack = vec![ROOT];
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index];
+            layout.push((node.id, node.balls.iter().copied().sorted().collect()));
+            stack.extend(node.children.iter().flatten());
         }
-        many = all(vec!(self.bottom_left.as_ref(), self.bottom_right.as_ref(), self.top_left.as_ref(), self.top_right.as_ref()),
-                   |item| item.is_none()) && self.balls.len() > 1;
-        let r: RenderingRect = RenderingRect { rect: Rect { x, y, w, h }, many };
-        rects.push(r);
+        layout.sort_by_key(|(id, _)| *id);
+        layout
+    }
 
-        return rects;
+    // pairs of the ball with every other ball in the deepest node it was inserted into
+    pub fn search(&self, id: usize) -> Vec<BallPairIds> {
+        match self.deepest.get(&id) {
+            Some(index) => self.nodes[*index]
+                .balls
+                .iter()
+                .filter(|other| **other != id)
+                .map(|other| BallPairIds { first: id, second: *other })
+                .collect(),
+            None => vec![],
+        }
     }
 
+    pub fn get_rectangles(&self) -> Vec<RenderingRect> {
+        let mut rects = vec![];
+        self.collect_rectangles(ROOT, &mut rects);
+        rects
+    }
 
-    pub fn info_collisions(&self) -> Vec<String> {
-        let mut lines = vec![];
+    fn collect_rectangles(&self, index: NodeIndex, rects: &mut Vec<RenderingRect>) {
+        let node = &self.nodes[index];
 
-        // if (self.is_collision_candidate || self.children_are_collision_candidates) {
-        //     let keys: Vec<String> = self.balls.keys().map(|key| key.to_string()).collect();
-        //     let keyo = keys.join(",");
-        //     lines.push(format!(" {} is:{} children:{} balls:{:#?}", self.id, self.is_collision_candidate, self.children_are_collision_candidates, keyo));
-        // } else {
-        //     return lines;
-        // }
-        //
-
-        if self.top_left.is_some() {
-            let node = self.top_left.as_ref().unwrap();
-            lines.extend(node.info_collisions());
+        if node.rect.w < 2.0 {
+            return;
         }
 
-        if self.top_right.is_some() {
-            let node = self.top_right.as_ref().unwrap();
-            lines.extend(node.info_collisions());
+        for child in node.children.iter().flatten() {
+            self.collect_rectangles(*child, rects);
         }
 
+        let many = node.children.iter().all(|child| child.is_none()) && node.balls.len() > 1;
+        rects.push(RenderingRect { rect: node.rect, many });
+    }
 
-        if self.bottom_left.is_some() {
-            let node = self.bottom_left.as_ref().unwrap();
-            lines.extend(node.info_collisions());
-        }
-
-        if self.bottom_right.is_some() {
-            let node = self.bottom_right.as_ref().unwrap();
-            lines.extend(node.info_collisions());
+    // leaf nodes with more than one ball, the ones that produce collision candidates
+    pub fn info_collisions(&self) -> Vec<String> {
+        let mut lines = vec![];
+        let mut stack = vec![ROOT];
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index];
+            if node.children.iter().all(|child| child.is_none()) && node.balls.len() > 1 {
+                lines.push(format!(" {} balls:{}", node.id, node.balls.iter().sorted().join(",")));
+            }
+            stack.extend(node.children.iter().flatten());
         }
-
-        return lines;
+        lines
     }
 
-    pub fn info_balls(&self) -> Vec<String> {
+    pub fn info_balls(&self, objects: &HashMap<usize, Ball>) -> Vec<String> {
         let mut lines = vec![];
 
-        for id in self.balls.keys().sorted() {
-            let ball = self.balls.get(id).expect("");
+        for id in self.nodes[ROOT].balls.iter().sorted() {
+            let ball = objects.get(id).expect("");
             lines.push(format!(
                 "{} {} {} {} {}", ball.ball_type, ball.pos.x, ball.pos.y, ball.next_position.x, ball.next_position.y));
 
         }
-        return lines;
+        lines
     }
 
-    pub fn info_ball_quads(&self) -> Vec<String> {
+    pub fn info_ball_quads(&self, objects: &HashMap<usize, Ball>) -> Vec<String> {
         let mut lines = vec![];
 
-        for id in self.balls.keys().sorted() {
+        for id in self.nodes[ROOT].balls.iter().sorted() {
             let quad_ids = self.search(*id);
             let quad_ids_str: Vec<String> = quad_ids
                 .iter()
@@ -296,25 +317,9 @@ impl QuadTreeNode {
                          .join("|"))
                 .collect();
             let line = quad_ids_str.join(",");
-            lines.push(format!(" ball id:{} {} is in {}", id, self.balls.get(id).expect("").color.to_string(), line));
+            lines.push(format!(" ball id:{} {} is in {}", id, objects.get(id).expect("").color, line));
         }
 
-        return lines;
+        lines
     }
 }
-
-//
-// fn get_child_collisions(node: &ChildNode) -> Vec<BallPair> {
-//     if node.is_some() {
-//         let cands = node.as_ref().unwrap().find_collision_candidates();
-//         if cands.len() > 0 {
-//             //console::log_1(&format!("candidates in this node: {:#?}", cands).into());
-//             return cands;
-//         }
-//     }
-//     return vec![];
-// }
-
-
-
-
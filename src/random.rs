@@ -1,29 +1,67 @@
-use wasm_bindgen::prelude::*;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 
-#[wasm_bindgen]
-extern "C" {
-    #[wasm_bindgen(js_namespace = Math)]
-    fn random() -> f64;
+// Seedable random source owned by the game state. StdRng is the same
+// algorithm on wasm and native, and we only ever draw f64s from it, so
+// a seed produces the same numbers on both targets.
+#[derive(Debug, Clone)]
+pub struct Random {
+    rng: StdRng,
 }
 
-#[cfg(target_family = "wasm")]
-pub fn random_range(min: usize, max: usize) -> usize {
-    (random() * (max - min) as f64).floor() as usize + min
-}
+impl Random {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
 
-#[cfg(target_family = "wasm")]
-pub fn random_range_descending(min: usize, max: usize) -> usize {
-    (random() * random() * (max - min) as f64).floor() as usize + min
-}
+    // every level gets its own stream, so a seed and a level id always give the same layout
+    pub fn for_level(seed: u64, level_id: usize) -> Self {
+        Self::new(seed ^ (level_id as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15))
+    }
 
-#[cfg(target_family = "wasm")]
-pub fn random_velocity(min: f64, max: f64) -> f64 {
-    (random() * (max - min)).floor() + min
-}
-#[cfg(target_family = "wasm")]
-pub fn random_sign() -> f64 {
-    if random() > 0.5 {
-        return 1.0;
+    pub fn random_seed() -> u64 {
+        rand::random()
+    }
+
+    fn next(&mut self) -> f64 {
+        self.rng.gen::<f64>()
+    }
+
+    pub fn random_range(&mut self, min: usize, max: usize) -> usize {
+        (self.next() * (max - min) as f64).floor() as usize + min
+    }
+
+    pub fn random_range_descending(&mut self, min: usize, max: usize) -> usize {
+        (self.next() * self.next() * (max - min) as f64).floor() as usize + min
+    }
+
+    pub fn random_velocity(&mut self, min: f64, max: f64) -> f64 {
+        (self.next() * (max - min)).floor() + min
+    }
+
+    // index into `weights`, picked with probability proportional to its weight
+    pub fn weighted_index(&mut self, weights: &[f64]) -> usize {
+        let mut remaining = self.next() * weights.iter().sum::<f64>();
+        for (index, weight) in weights.iter().enumerate() {
+            if remaining < *weight {
+                return index;
+            }
+            remaining -= weight;
+        }
+        weights.len() - 1
+    }
+
+    // true with probability `p`
+    pub fn chance(&mut self, p: f64) -> bool {
+        self.next() < p
+    }
+
+    pub fn random_sign(&mut self) -> f64 {
+        if self.next() > 0.5 {
+            return 1.0;
+        }
+        -1.0
     }
-    return -1.0;
 }
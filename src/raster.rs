@@ -0,0 +1,305 @@
+use crate::ball::{Color, WHITE};
+use crate::draw::{Draw, Stroke};
+use crate::geometry::{Point, Rect};
+use std::fmt;
+
+// classic 5x7 font for ' ' to '~', one byte per column, lowest bit is the top row
+const FONT: [[u8; 5]; 95] = [
+    [0x00, 0x00, 0x00, 0x00, 0x00],
+    [0x00, 0x00, 0x5F, 0x00, 0x00],
+    [0x00, 0x07, 0x00, 0x07, 0x00],
+    [0x14, 0x7F, 0x14, 0x7F, 0x14],
+    [0x24, 0x2A, 0x7F, 0x2A, 0x12],
+    [0x23, 0x13, 0x08, 0x64, 0x62],
+    [0x36, 0x49, 0x55, 0x22, 0x50],
+    [0x00, 0x05, 0x03, 0x00, 0x00],
+    [0x00, 0x1C, 0x22, 0x41, 0x00],
+    [0x00, 0x41, 0x22, 0x1C, 0x00],
+    [0x08, 0x2A, 0x1C, 0x2A, 0x08],
+    [0x08, 0x08, 0x3E, 0x08, 0x08],
+    [0x00, 0x50, 0x30, 0x00, 0x00],
+    [0x08, 0x08, 0x08, 0x08, 0x08],
+    [0x00, 0x60, 0x60, 0x00, 0x00],
+    [0x20, 0x10, 0x08, 0x04, 0x02],
+    [0x3E, 0x51, 0x49, 0x45, 0x3E],
+    [0x00, 0x42, 0x7F, 0x40, 0x00],
+    [0x42, 0x61, 0x51, 0x49, 0x46],
+    [0x21, 0x41, 0x45, 0x4B, 0x31],
+    [0x18, 0x14, 0x12, 0x7F, 0x10],
+    [0x27, 0x45, 0x45, 0x45, 0x39],
+    [0x3C, 0x4A, 0x49, 0x49, 0x30],
+    [0x01, 0x71, 0x09, 0x05, 0x03],
+    [0x36, 0x49, 0x49, 0x49, 0x36],
+    [0x06, 0x49, 0x49, 0x29, 0x1E],
+    [0x00, 0x36, 0x36, 0x00, 0x00],
+    [0x00, 0x56, 0x36, 0x00, 0x00],
+    [0x00, 0x08, 0x14, 0x22, 0x41],
+    [0x14, 0x14, 0x14, 0x14, 0x14],
+    [0x41, 0x22, 0x14, 0x08, 0x00],
+    [0x02, 0x01, 0x51, 0x09, 0x06],
+    [0x32, 0x49, 0x79, 0x41, 0x3E],
+    [0x7E, 0x11, 0x11, 0x11, 0x7E],
+    [0x7F, 0x49, 0x49, 0x49, 0x36],
+    [0x3E, 0x41, 0x41, 0x41, 0x22],
+    [0x7F, 0x41, 0x41, 0x22, 0x1C],
+    [0x7F, 0x49, 0x49, 0x49, 0x41],
+    [0x7F, 0x09, 0x09, 0x01, 0x01],
+    [0x3E, 0x41, 0x41, 0x51, 0x32],
+    [0x7F, 0x08, 0x08, 0x08, 0x7F],
+    [0x00, 0x41, 0x7F, 0x41, 0x00],
+    [0x20, 0x40, 0x41, 0x3F, 0x01],
+    [0x7F, 0x08, 0x14, 0x22, 0x41],
+    [0x7F, 0x40, 0x40, 0x40, 0x40],
+    [0x7F, 0x02, 0x04, 0x02, 0x7F],
+    [0x7F, 0x04, 0x08, 0x10, 0x7F],
+    [0x3E, 0x41, 0x41, 0x41, 0x3E],
+    [0x7F, 0x09, 0x09, 0x09, 0x06],
+    [0x3E, 0x41, 0x51, 0x21, 0x5E],
+    [0x7F, 0x09, 0x19, 0x29, 0x46],
+    [0x46, 0x49, 0x49, 0x49, 0x31],
+    [0x01, 0x01, 0x7F, 0x01, 0x01],
+    [0x3F, 0x40, 0x40, 0x40, 0x3F],
+    [0x1F, 0x20, 0x40, 0x20, 0x1F],
+    [0x7F, 0x20, 0x18, 0x20, 0x7F],
+    [0x63, 0x14, 0x08, 0x14, 0x63],
+    [0x03, 0x04, 0x78, 0x04, 0x03],
+    [0x61, 0x51, 0x49, 0x45, 0x43],
+    [0x00, 0x00, 0x7F, 0x41, 0x41],
+    [0x02, 0x04, 0x08, 0x10, 0x20],
+    [0x41, 0x41, 0x7F, 0x00, 0x00],
+    [0x04, 0x02, 0x01, 0x02, 0x04],
+    [0x40, 0x40, 0x40, 0x40, 0x40],
+    [0x00, 0x01, 0x02, 0x04, 0x00],
+    [0x20, 0x54, 0x54, 0x54, 0x78],
+    [0x7F, 0x48, 0x44, 0x44, 0x38],
+    [0x38, 0x44, 0x44, 0x44, 0x20],
+    [0x38, 0x44, 0x44, 0x48, 0x7F],
+    [0x38, 0x54, 0x54, 0x54, 0x18],
+    [0x08, 0x7E, 0x09, 0x01, 0x02],
+    [0x08, 0x14, 0x54, 0x54, 0x3C],
+    [0x7F, 0x08, 0x04, 0x04, 0x78],
+    [0x00, 0x44, 0x7D, 0x40, 0x00],
+    [0x20, 0x40, 0x44, 0x3D, 0x00],
+    [0x00, 0x7F, 0x10, 0x28, 0x44],
+    [0x00, 0x41, 0x7F, 0x40, 0x00],
+    [0x7C, 0x04, 0x18, 0x04, 0x78],
+    [0x7C, 0x08, 0x04, 0x04, 0x78],
+    [0x38, 0x44, 0x44, 0x44, 0x38],
+    [0x7C, 0x14, 0x14, 0x14, 0x08],
+    [0x08, 0x14, 0x14, 0x18, 0x7C],
+    [0x7C, 0x08, 0x04, 0x04, 0x08],
+    [0x48, 0x54, 0x54, 0x54, 0x20],
+    [0x04, 0x3F, 0x44, 0x40, 0x20],
+    [0x3C, 0x40, 0x40, 0x20, 0x7C],
+    [0x1C, 0x20, 0x40, 0x20, 0x1C],
+    [0x3C, 0x40, 0x30, 0x40, 0x3C],
+    [0x44, 0x28, 0x10, 0x28, 0x44],
+    [0x0C, 0x50, 0x50, 0x50, 0x3C],
+    [0x44, 0x64, 0x54, 0x4C, 0x44],
+    [0x00, 0x08, 0x36, 0x41, 0x00],
+    [0x00, 0x00, 0x7F, 0x00, 0x00],
+    [0x00, 0x41, 0x36, 0x08, 0x00],
+    [0x08, 0x04, 0x08, 0x10, 0x08],
+];
+
+// font pixels are drawn as 2x2 blocks, so a line is 14 pixels high like the canvas font
+const FONT_SCALE: usize = 2;
+
+#[derive(Debug)]
+pub enum PngError {
+    Encoding(String),
+}
+
+impl fmt::Display for PngError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PngError::Encoding(err) => write!(f, "can't encode png: {}", err),
+        }
+    }
+}
+
+// Draws into an RGB pixel buffer on the CPU, one pixel per arena unit. Starts out white
+// like the canvas on the page. Circle edges are anti-aliased, rectangles and text aren't.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Raster {
+    rect: Rect,
+    width: usize,
+    height: usize,
+    alpha: f64,
+    pixels: Vec<Color>,
+}
+
+impl Raster {
+    pub fn new(rect: Rect) -> Self {
+        let width = rect.w.ceil().max(0.0) as usize;
+        let height = rect.h.ceil().max(0.0) as usize;
+        Self {
+            rect,
+            width,
+            height,
+            alpha: 1.0,
+            pixels: vec![WHITE; width * height],
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    // pixel in the given column and row
+    pub fn pixel(&self, col: usize, row: usize) -> Color {
+        self.pixels[row * self.width + col]
+    }
+
+    // rows of RGB bytes, top to bottom
+    pub fn rgb(&self) -> Vec<u8> {
+        self.pixels
+            .iter()
+            .flat_map(|color| [color.r, color.g, color.b])
+            .collect()
+    }
+
+    pub fn to_png(&self) -> Result<Vec<u8>, PngError> {
+        let mut bytes = vec![];
+        let mut encoder = png::Encoder::new(&mut bytes, self.width as u32, self.height as u32);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder
+            .write_header()
+            .map_err(|err| PngError::Encoding(err.to_string()))?;
+        writer
+            .write_image_data(&self.rgb())
+            .map_err(|err| PngError::Encoding(err.to_string()))?;
+        writer
+            .finish()
+            .map_err(|err| PngError::Encoding(err.to_string()))?;
+        Ok(bytes)
+    }
+
+    // mixes `color` into a pixel, `coverage` is the part of the pixel it covers
+    fn blend(&mut self, col: i64, row: i64, color: Color, coverage: f64) {
+        if col < 0 || row < 0 || col >= self.width as i64 || row >= self.height as i64 {
+            return;
+        }
+        let a = coverage.clamp(0.0, 1.0) * self.alpha;
+        if a <= 0.0 {
+            return;
+        }
+        let pixel = &mut self.pixels[row as usize * self.width + col as usize];
+        let mix = |dst: u8, src: u8| (src as f64 * a + dst as f64 * (1.0 - a)).round() as u8;
+        *pixel = Color {
+            r: mix(pixel.r, color.r),
+            g: mix(pixel.g, color.g),
+            b: mix(pixel.b, color.b),
+        };
+    }
+
+    // Blends `color` into every pixel within `reach` of `center`, `coverage` turns the
+    // distance of the pixel's center into the part of the pixel covered.
+    fn each_pixel_near(
+        &mut self,
+        center: Point,
+        reach: f64,
+        color: Color,
+        coverage: impl Fn(f64) -> f64,
+    ) {
+        let cx = center.x - self.rect.x;
+        let cy = center.y - self.rect.y;
+        let first_col = (cx - reach).floor() as i64;
+        let last_col = (cx + reach).ceil() as i64;
+        let first_row = (cy - reach).floor() as i64;
+        let last_row = (cy + reach).ceil() as i64;
+
+        for row in first_row.max(0)..=last_row.min(self.height as i64 - 1) {
+            for col in first_col.max(0)..=last_col.min(self.width as i64 - 1) {
+                let dx = col as f64 + 0.5 - cx;
+                let dy = row as f64 + 0.5 - cy;
+                let distance = (dx * dx + dy * dy).sqrt();
+                self.blend(col, row, color, coverage(distance));
+            }
+        }
+    }
+
+    fn fill_box(&mut self, col: i64, row: i64, w: i64, h: i64, color: Color) {
+        for r in row..row + h {
+            for c in col..col + w {
+                self.blend(c, r, color, 1.0);
+            }
+        }
+    }
+}
+
+impl Draw for Raster {
+    fn clear(&mut self, rect: Rect) {
+        let alpha = self.alpha;
+        self.alpha = 1.0;
+        let col = (rect.x - self.rect.x).floor() as i64;
+        let row = (rect.y - self.rect.y).floor() as i64;
+        self.fill_box(col, row, rect.w.ceil() as i64, rect.h.ceil() as i64, WHITE);
+        self.alpha = alpha;
+    }
+
+    fn alpha(&mut self, alpha: f64) {
+        self.alpha = alpha;
+    }
+
+    fn circle(&mut self, center: Point, radius: f64, fill: Option<Color>, stroke: Option<Stroke>) {
+        // coverage falls off linearly over the pixel the edge crosses
+        if let Some(color) = fill {
+            self.each_pixel_near(center, radius + 1.0, color, |distance| {
+                radius + 0.5 - distance
+            });
+        }
+        if let Some(stroke) = stroke {
+            let half = stroke.width / 2.0;
+            self.each_pixel_near(center, radius + half + 1.0, stroke.color, |distance| {
+                half + 0.5 - (distance - radius).abs()
+            });
+        }
+    }
+
+    fn rect(&mut self, rect: Rect, color: Color) {
+        let col = (rect.x - self.rect.x).round() as i64;
+        let row = (rect.y - self.rect.y).round() as i64;
+        let w = rect.w.round() as i64;
+        let h = rect.h.round() as i64;
+        if w <= 0 || h <= 0 {
+            return;
+        }
+
+        self.fill_box(col, row, w, 1, color);
+        self.fill_box(col, row + h - 1, w, 1, color);
+        if h > 2 {
+            self.fill_box(col, row + 1, 1, h - 2, color);
+            self.fill_box(col + w - 1, row + 1, 1, h - 2, color);
+        }
+    }
+
+    fn text(&mut self, x: f64, y: f64, text: &str, color: Color) {
+        let left = (x - self.rect.x).round() as i64;
+        // capitals sit on the baseline, the font has no descenders
+        let top = (y - self.rect.y).round() as i64 - 7 * FONT_SCALE as i64;
+        let scale = FONT_SCALE as i64;
+
+        for (i, c) in text.chars().enumerate() {
+            let glyph = match c {
+                ' '..='~' => FONT[c as usize - ' ' as usize],
+                _ => FONT['?' as usize - ' ' as usize],
+            };
+            let glyph_left = left + i as i64 * 6 * scale;
+            for (col, bits) in glyph.iter().enumerate() {
+                for row in 0..7 {
+                    if bits & (1 << row) != 0 {
+                        let c = glyph_left + col as i64 * scale;
+                        let r = top + row as i64 * scale;
+                        self.fill_box(c, r, scale, scale, color);
+                    }
+                }
+            }
+        }
+    }
+}
@@ -1,18 +1,32 @@
-use crate::ball::{Ball, Color, BLACK, RED};
-use crate::GameState;
-use std::f64::consts::PI;
-use web_sys::{console, CanvasGradient, CanvasRenderingContext2d};
+use crate::ball::{Ball, BLACK, GOLD, RED};
+use crate::constants::FULL;
+use crate::draw::{Draw, Stroke};
+use crate::geometry::Point;
+use crate::logic::GameState;
+use crate::raster::{PngError, Raster};
+use crate::solver::Shot;
+use crate::svg::Svg;
 
 pub struct Renderer<'a> {
     pub gamestate: &'a GameState,
+    // balls are drawn this far between their previous tick and the current one, 0 to 1
+    pub interpolation: f64,
 }
 
-impl Renderer<'static> {
-    pub fn new(game: GameState) -> Self {
-        Self { gamestate: &game }
+impl<'a> Renderer<'a> {
+    pub fn new(gamestate: &'a GameState) -> Self {
+        Self::interpolated(gamestate, 1.0)
     }
 
-    pub fn render_state(&self, ctx: &mut CanvasRenderingContext2d, clear: bool) {
+    // for display frames that fall between two ticks, see `timestep::FixedStep`
+    pub fn interpolated(gamestate: &'a GameState, interpolation: f64) -> Self {
+        Self {
+            gamestate,
+            interpolation,
+        }
+    }
+
+    pub fn render_state(&self, ctx: &mut dyn Draw, clear: bool) {
         let state = self.gamestate;
         if clear {
             self.clear_canvas(ctx);
@@ -21,162 +35,155 @@ impl Renderer<'static> {
         if state.objects.is_empty() {
             return;
         }
-        // for (_, obj) in &self.objects {
-        // draw_ball(ctx, &obj);
-        //  let bb = obj.bounding_rect_current();
-        //draw_rect(&ctx,bb.x, bb.y, bb.w, bb.h, BLACK);
-        // }
-
-        state
-            .objects
-            .values()
+
+        // ordered by id, so balls overlap the same way every frame
+        let mut balls: Vec<&Ball> = state.objects.values().collect();
+        balls.sort_by_key(|ball| ball.id);
+        balls
             .into_iter()
-            .for_each(|obj| draw_ball(ctx, obj));
+            .for_each(|obj| draw_ball(ctx, &obj.interpolated(self.interpolation)));
     }
 
-    pub fn render_won(&self, ctx: &mut CanvasRenderingContext2d) {
-        self.clear_canvas(ctx);
+    // where to click and what it gets, drawn over the paused game
+    pub fn render_hint(&self, ctx: &mut dyn Draw, shot: &Shot) {
+        draw_ghost(ctx, shot.point, FULL);
+        ctx.alpha(1.0);
+        ctx.circle(shot.point, 3.0, Some(GOLD), None);
 
-        write_text(ctx, 30.0, 50.0, "Congratz! You won.");
+        let captures = match shot.captured {
+            1 => "captures 1 ball".to_string(),
+            n => format!("captures {} balls", n),
+        };
         write_text(
             ctx,
-            30.0,
-            70.0,
-            "Click canvas or press 'n' to go to the next level",
+            10.0,
+            20.0,
+            &format!("hint: click the gold circle, {}", captures),
         );
     }
 
-    pub fn render_lost(&self, ctx: &mut CanvasRenderingContext2d) {
+    // the capture ball at full size under the mouse, with rings around the balls it would capture
+    pub fn render_preview(&self, ctx: &mut dyn Draw, point: Point, captured: &[usize]) {
+        draw_ghost(ctx, point, FULL);
+
+        for id in captured {
+            if let Some(ball) = self.gamestate.objects.get(id) {
+                // where the ball is drawn this frame
+                let ball = ball.interpolated(self.interpolation);
+                let stroke = Stroke {
+                    color: GOLD,
+                    width: 2.0,
+                };
+                ctx.circle(ball.pos, ball.radius + 3.0, None, Some(stroke));
+            }
+        }
+    }
+
+    pub fn render_won(&self, ctx: &mut dyn Draw) {
+        self.clear_canvas(ctx);
+
+        write_text(ctx, 30.0, 50.0, "Congratz! You won.");
+        let next = if self.gamestate.is_last_level() {
+            "Click canvas or press 'n' to start over from level 1"
+        } else {
+            "Click canvas or press 'n' to go to the next level"
+        };
+        write_text(ctx, 30.0, 70.0, next);
+    }
+
+    pub fn render_lost(&self, ctx: &mut dyn Draw) {
         self.clear_canvas(ctx);
         write_text(ctx, 10.0, 20.0, "you lost");
     }
 
-    pub fn render_debug_collision_info(&self, ctx: &CanvasRenderingContext2d) {
-        let tree = self.gamestate.tree;
+    pub fn render_debug_collision_info(&self, ctx: &mut dyn Draw) {
         self.clear_canvas(ctx);
         let mut i = 0.0;
 
-        if tree.is_none() {
-            return;
-        }
-
-        for line in tree.as_ref().unwrap().info_collisions().iter() {
+        for line in self.gamestate.broad_phase.info_collisions().iter() {
             write_text(ctx, 10.0, 20.0 + 15.0 * i, format!("{:#?}", line).as_str());
             i += 1.0;
         }
     }
 
-    pub fn render_debug_ball_quad_info(&self, ctx: &CanvasRenderingContext2d) {
+    pub fn render_debug_ball_quad_info(&self, ctx: &mut dyn Draw) {
         self.clear_canvas(ctx);
-        let tree = self.gamestate.tree;
         let mut i = 0.0;
 
-        if tree.is_none() {
-            return;
-        }
-
-        tree.as_ref().unwrap().info_balls().iter().for_each(|line| {
+        self.gamestate.info_balls().iter().for_each(|line| {
             write_text(ctx, 10.0, 20.0 + 15.0 * i, format!("{:#?}", line).as_str());
             i += 1.0;
         });
     }
 
-    pub fn clear_canvas(&self, ctx: &CanvasRenderingContext2d) {
-        ctx.clear_rect(0.0, 0.0, self.gamestate.rect.w, self.gamestate.rect.h);
+    pub fn clear_canvas(&self, ctx: &mut dyn Draw) {
+        ctx.clear(self.gamestate.rect);
     }
 
-    pub fn render_quad_tree(&self, ctx: &CanvasRenderingContext2d, clear: bool) {
+    // The current frame as a standalone SVG document, for bug reports and level thumbnails.
+    // With `quad_tree` the broad phase cells are outlined too: quadtree nodes or occupied
+    // grid cells. Brute force and sweep and prune have no cells to draw.
+    pub fn to_svg(&self, quad_tree: bool) -> String {
+        let mut svg = Svg::new(self.gamestate.rect);
+        self.render_frame(&mut svg, quad_tree);
+        svg.finish()
+    }
+
+    // the current frame as a PNG image, one pixel per arena unit
+    pub fn to_png(&self, quad_tree: bool) -> Result<Vec<u8>, PngError> {
+        let mut raster = Raster::new(self.gamestate.rect);
+        self.render_frame(&mut raster, quad_tree);
+        raster.to_png()
+    }
+
+    // balls and optionally the broad phase cells on top, for the exporters
+    pub fn render_frame(&self, ctx: &mut dyn Draw, quad_tree: bool) {
+        self.render_state(ctx, false);
+        if quad_tree {
+            self.render_quad_tree(ctx, false);
+        }
+    }
+
+    pub fn render_quad_tree(&self, ctx: &mut dyn Draw, clear: bool) {
         if clear {
             self.clear_canvas(ctx);
         }
 
         let rects = self.gamestate.get_rectangles();
 
+        ctx.alpha(1.0);
         for r in rects {
             if r.many {
-                draw_rect(ctx, r.rect.x, r.rect.y, r.rect.w, r.rect.h, RED);
+                ctx.rect(r.rect, RED);
             } else {
-                draw_rect(ctx, r.rect.x, r.rect.y, r.rect.w, r.rect.h, BLACK);
+                ctx.rect(r.rect, BLACK);
             }
         }
     }
 }
 
-fn draw_ball_xy(ctx: &mut CanvasRenderingContext2d, x: f64, y: f64, radius: f64, color: Color) {
-    ctx.begin_path();
-    ctx.set_global_alpha(0.8);
-    // let grd = ctx.create_radial_gradient(0.0,0.0,0.0, color.r as f64, color.g as f64, color.b as f64);
-    //  let grd: CanvasGradient = ctx.create_linear_gradient(
-    //      x-radius,
-    //      y-radius,
-    //      x+radius,
-    //      y+radius
-    //  );
-
-    //    let grd: CanvasGradient = ctx
-    //      .create_radial_gradient(x, y, 3.3 * radius / 4.0, x, y, radius)
-    //    .unwrap();
-
-    // grd.add_color_stop(0.0, &color.to_string());
-    // grd.add_color_stop(1.0, &WHITE.to_string());
-    //grd.add_color_stop(0.5, &WHITE.to_string());
-
-    ctx.begin_path();
-    ctx.set_fill_style(&color.to_string().into());
-    // //ctx.set_fill_style(&color.to_string().into());
-    // ctx.arc(x, y, radius, 0.0, 1.0 * PI);
-    // ctx.close_path();
-    // ctx.fill();
-    // ctx.stroke();
-    //ctx.set_fill_style(&grd);
-    ctx.arc(x, y, radius, 0.0 * PI, 2.0 * PI);
-    ctx.fill();
-    ctx.stroke();
-    ctx.close_path();
-}
-
-fn draw_rect(
-    ctx: &CanvasRenderingContext2d,
-    x: f64,
-    y: f64,
-    width: f64,
-    height: f64,
-    color: Color,
-) {
-    ctx.begin_path();
-    ctx.set_fill_style(&color.to_string().into());
-    ctx.set_stroke_style(&color.to_string().into());
-    ctx.stroke_rect(x, y, width, height);
-    ctx.stroke();
+// translucent disc with a solid outline
+fn draw_ghost(ctx: &mut dyn Draw, center: Point, radius: f64) {
+    ctx.alpha(0.3);
+    ctx.circle(center, radius, Some(GOLD), None);
+    ctx.alpha(1.0);
+    let stroke = Stroke {
+        color: GOLD,
+        width: 1.0,
+    };
+    ctx.circle(center, radius, None, Some(stroke));
 }
 
-fn draw_active_rect(
-    ctx: &CanvasRenderingContext2d,
-    x: f64,
-    y: f64,
-    width: f64,
-    height: f64,
-    color: Color,
-) {
-    ctx.begin_path();
-    ctx.set_fill_style(&color.to_string().into());
-    ctx.set_stroke_style(&color.to_string().into());
-    ctx.fill_rect(x, y, width, height);
-    ctx.stroke();
+pub fn draw_ball(ctx: &mut dyn Draw, obj: &Ball) {
+    let outline = Stroke {
+        color: BLACK,
+        width: 1.0,
+    };
+    ctx.alpha(0.8);
+    ctx.circle(obj.pos, obj.radius, Some(obj.color), Some(outline));
 }
 
-pub fn draw_ball(ctx: &mut CanvasRenderingContext2d, obj: &Ball) {
-    draw_ball_xy(ctx, obj.pos.x, obj.pos.y, obj.radius, obj.color);
-}
-
-pub fn write_text(ctx: &CanvasRenderingContext2d, x: f64, y: f64, txt: &str) {
-    ctx.set_font("14px Verdana");
-    ctx.set_fill_style(&BLACK.to_string().into());
-    let result = ctx.fill_text(txt, x, y);
-    match result {
-        Ok(_) => {}
-        Err(_) => {
-            console::log_1(&format!("error filling text: {}", txt).into());
-        }
-    }
+pub fn write_text(ctx: &mut dyn Draw, x: f64, y: f64, txt: &str) {
+    ctx.alpha(1.0);
+    ctx.text(x, y, txt, BLACK);
 }
@@ -0,0 +1,309 @@
+use crate::ball::BallType;
+use crate::constants::{HEIGHT, WIDTH};
+use crate::levels::{Level, LevelError, LevelPack};
+use crate::logic::{ChangeState, GameState};
+use std::fmt;
+use std::str::FromStr;
+
+// bumped whenever recorded games would play out differently, old replays are rejected
+const VERSION: u32 = 3;
+const HEADER: &str = "circles-replay";
+
+// Everything the player can do to a running game. All input goes through
+// `GameState::apply_input`, which is also where it gets recorded.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Input {
+    Click { x: f64, y: f64 },
+    Change(ChangeState),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ReplayEvent {
+    // frame of the current level at which the input happened
+    pub frame_id: usize,
+    pub input: Input,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Replay {
+    pub seed: u64,
+    // arena size, layouts depend on it
+    pub width: usize,
+    pub height: usize,
+    // the whole campaign, so games on a custom level pack play back on the same levels
+    pub levels: Vec<Level>,
+    pub events: Vec<ReplayEvent>,
+}
+
+impl Replay {
+    // classic levels in the classic 640x480 arena
+    pub fn new(seed: u64) -> Self {
+        Self::with_arena(seed, WIDTH, HEIGHT)
+    }
+
+    pub fn with_arena(seed: u64, width: usize, height: usize) -> Self {
+        Self::with_levels(seed, width, height, LevelPack::classic().levels)
+    }
+
+    pub fn with_levels(seed: u64, width: usize, height: usize, levels: Vec<Level>) -> Self {
+        Self {
+            seed,
+            width,
+            height,
+            levels,
+            events: vec![],
+        }
+    }
+
+    pub fn record(&mut self, frame_id: usize, input: Input) {
+        self.events.push(ReplayEvent { frame_id, input });
+    }
+}
+
+// Text format, one level and then one event per line. A level is shots, balls and balls to
+// capture, optionally followed by ball types with their weights:
+//
+//   circles-replay 3
+//   seed 42
+//   arena 640 480
+//   level 1 5 2
+//   level 2 30 25 RED_BALL:3 SILVER_BALL:1
+//   12 click 320 240
+//   80 key play-pause
+impl fmt::Display for Replay {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{} {}", HEADER, VERSION)?;
+        writeln!(f, "seed {}", self.seed)?;
+        writeln!(f, "arena {} {}", self.width, self.height)?;
+        for level in &self.levels {
+            write!(
+                f,
+                "level {} {} {}",
+                level.max_shots, level.num_of_balls, level.num_captured
+            )?;
+            for (ball_type, weight) in &level.ball_types {
+                write!(f, " {}:{}", ball_type.name(), weight)?;
+            }
+            writeln!(f)?;
+        }
+        for event in &self.events {
+            match event.input {
+                Input::Click { x, y } => writeln!(f, "{} click {} {}", event.frame_id, x, y)?,
+                Input::Change(change) => {
+                    writeln!(f, "{} key {}", event.frame_id, change_state_name(change))?
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Replay {
+    type Err = ReplayError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let mut lines = text
+            .lines()
+            .enumerate()
+            .map(|(i, line)| (i + 1, line.trim()))
+            .filter(|(_, line)| !line.is_empty())
+            .peekable();
+
+        let header = lines
+            .next()
+            .map(|(_, line)| line.split_whitespace().collect::<Vec<_>>());
+        match header.as_deref() {
+            Some([HEADER, version]) if *version == VERSION.to_string() => {}
+            Some([HEADER, version]) => {
+                return Err(ReplayError::UnsupportedVersion(version.to_string()))
+            }
+            _ => return Err(ReplayError::MissingHeader),
+        }
+
+        let seed = match lines.next() {
+            Some((line_no, line)) => match line.split_whitespace().collect::<Vec<_>>()[..] {
+                ["seed", seed] => parse_field(line_no, seed)?,
+                _ => return Err(ReplayError::InvalidLine(line_no, line.to_string())),
+            },
+            None => return Err(ReplayError::MissingSeed),
+        };
+
+        let mut replay = match lines.next() {
+            Some((line_no, line)) => match line.split_whitespace().collect::<Vec<_>>()[..] {
+                ["arena", width, height] => Replay::with_arena(
+                    seed,
+                    parse_field(line_no, width)?,
+                    parse_field(line_no, height)?,
+                ),
+                _ => return Err(ReplayError::InvalidLine(line_no, line.to_string())),
+            },
+            None => return Err(ReplayError::MissingArena),
+        };
+
+        replay.levels.clear();
+        while let Some((line_no, line)) = lines.next_if(|(_, line)| line.starts_with("level")) {
+            let level = parse_level(line_no, line)?;
+            level
+                .validate()
+                .map_err(|err| ReplayError::InvalidLevel(line_no, err))?;
+            replay.levels.push(level);
+        }
+        if replay.levels.is_empty() {
+            return Err(ReplayError::MissingLevels);
+        }
+
+        for (line_no, line) in lines {
+            let input = match line.split_whitespace().collect::<Vec<_>>()[..] {
+                [frame_id, "click", x, y] => (
+                    frame_id,
+                    Input::Click {
+                        x: parse_field(line_no, x)?,
+                        y: parse_field(line_no, y)?,
+                    },
+                ),
+                [frame_id, "key", name] => match change_state_from_name(name) {
+                    Some(change) => (frame_id, Input::Change(change)),
+                    None => return Err(ReplayError::InvalidLine(line_no, line.to_string())),
+                },
+                _ => return Err(ReplayError::InvalidLine(line_no, line.to_string())),
+            };
+            replay.record(parse_field(line_no, input.0)?, input.1);
+        }
+
+        Ok(replay)
+    }
+}
+
+fn parse_level(line_no: usize, line: &str) -> Result<Level, ReplayError> {
+    let invalid = || ReplayError::InvalidLine(line_no, line.to_string());
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    let (shots, balls, capture, ball_types) = match fields[..] {
+        ["level", shots, balls, capture, ref ball_types @ ..] => {
+            (shots, balls, capture, ball_types)
+        }
+        _ => return Err(invalid()),
+    };
+
+    let mut level = Level::new(
+        parse_field(line_no, shots)?,
+        parse_field(line_no, balls)?,
+        parse_field(line_no, capture)?,
+    );
+    for entry in ball_types {
+        let (name, weight) = entry.split_once(':').ok_or_else(invalid)?;
+        let ball_type = BallType::from_name(name).ok_or_else(|| {
+            ReplayError::InvalidLevel(line_no, LevelError::UnknownBallType(name.to_string()))
+        })?;
+        level
+            .ball_types
+            .push((ball_type, parse_field(line_no, weight)?));
+    }
+    Ok(level)
+}
+
+fn parse_field<T: FromStr>(line_no: usize, field: &str) -> Result<T, ReplayError> {
+    field
+        .parse()
+        .map_err(|_| ReplayError::InvalidNumber(line_no, field.to_string()))
+}
+
+fn change_state_name(change: ChangeState) -> &'static str {
+    match change {
+        ChangeState::PlayPause => "play-pause",
+        ChangeState::NextLevel => "next-level",
+        ChangeState::RestartLevel => "restart-level",
+        ChangeState::Quit => "quit",
+        ChangeState::NoChange => "no-change",
+    }
+}
+
+fn change_state_from_name(name: &str) -> Option<ChangeState> {
+    match name {
+        "play-pause" => Some(ChangeState::PlayPause),
+        "next-level" => Some(ChangeState::NextLevel),
+        "restart-level" => Some(ChangeState::RestartLevel),
+        "quit" => Some(ChangeState::Quit),
+        "no-change" => Some(ChangeState::NoChange),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReplayError {
+    MissingHeader,
+    // recorded by an older game, its layouts would come out differently
+    UnsupportedVersion(String),
+    MissingSeed,
+    MissingArena,
+    MissingLevels,
+    InvalidLevel(usize, LevelError),
+    InvalidLine(usize, String),
+    InvalidNumber(usize, String),
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReplayError::MissingHeader => {
+                write!(f, "replay should start with '{} {}'", HEADER, VERSION)
+            }
+            ReplayError::UnsupportedVersion(version) => write!(
+                f,
+                "replay version {} can't be played back, only version {}",
+                version, VERSION
+            ),
+            ReplayError::MissingSeed => write!(f, "replay is missing the seed line"),
+            ReplayError::MissingArena => write!(f, "replay is missing the arena line"),
+            ReplayError::MissingLevels => write!(f, "replay is missing its levels"),
+            ReplayError::InvalidLevel(line_no, err) => write!(f, "line {}: {}", line_no, err),
+            ReplayError::InvalidLine(line_no, line) => {
+                write!(f, "line {}: can't parse '{}'", line_no, line)
+            }
+            ReplayError::InvalidNumber(line_no, field) => {
+                write!(f, "line {}: '{}' is not a valid number", line_no, field)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ReplayError {}
+
+// Feeds a recording back into a game, one frame at a time.
+#[derive(Debug, Clone)]
+pub struct Playback {
+    replay: Replay,
+    cursor: usize,
+}
+
+impl Playback {
+    pub fn new(replay: Replay) -> Self {
+        Self { replay, cursor: 0 }
+    }
+
+    // a fresh game in the state the recording started from
+    pub fn new_game(&self) -> GameState {
+        GameState::with_levels(
+            self.replay.levels.clone(),
+            self.replay.width,
+            self.replay.height,
+            self.replay.seed,
+        )
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.cursor >= self.replay.events.len()
+    }
+
+    // applies all inputs recorded at the current frame, then advances the game
+    pub fn step(&mut self, game: &mut GameState) {
+        while let Some(event) = self.replay.events.get(self.cursor) {
+            if event.frame_id != game.frame_id {
+                break;
+            }
+            game.apply_input(event.input);
+            self.cursor += 1;
+        }
+
+        game.advance();
+    }
+}
@@ -0,0 +1,200 @@
+use crate::ball::BallState::{Expanding, Normal, Shrinking};
+use crate::broadphase::BroadPhaseKind;
+use crate::geometry::Point;
+use crate::logic::{GameResult, GameState};
+use crate::player::Player;
+use crate::replay::Input;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SolverConfig {
+    // latest click, in frames from now
+    pub max_delay: usize,
+    // frames between the click times tried
+    pub delay_step: usize,
+    // distance between the grid points tried, ball positions are always tried too
+    pub grid_step: f64,
+    // gives up on a capture chain after this many frames
+    pub horizon: usize,
+}
+
+impl SolverConfig {
+    // thorough search for offline analysis
+    pub fn offline() -> Self {
+        Self {
+            max_delay: 60,
+            delay_step: 10,
+            grid_step: 40.0,
+            horizon: 3_000,
+        }
+    }
+
+    // clicks right away on a coarse grid, cheap enough to run on a key press
+    pub fn hint() -> Self {
+        Self {
+            max_delay: 0,
+            delay_step: 1,
+            grid_step: 80.0,
+            horizon: 3_000,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Shot {
+    // frames to wait before clicking
+    pub delay: usize,
+    pub point: Point,
+    // balls the shot captures, not counting the capture ball
+    pub captured: usize,
+}
+
+// Finds the click that captures the most balls by trying every candidate on a copy of
+// the game. The simulation is deterministic, so the captures found are the ones the
+// shot will get. Ties go to the earliest click, then the first point tried.
+pub fn solve(game: &GameState, config: &SolverConfig) -> Option<Shot> {
+    if game.shots == 0 || game.check_win_lose() != GameResult::Playing {
+        return None;
+    }
+
+    let mut best: Option<Shot> = None;
+    // sweep and prune plays the same as any other broad phase, only faster
+    let mut waiting = game.sandbox(BroadPhaseKind::SweepAndPrune);
+    for delay in 0..=config.max_delay {
+        if delay % config.delay_step.max(1) == 0 {
+            for point in candidates(&waiting, config.grid_step) {
+                let captured = captures(&waiting, point, config.horizon);
+                if best.is_none_or(|shot| captured > shot.captured) {
+                    best = Some(Shot {
+                        delay,
+                        point,
+                        captured,
+                    });
+                }
+            }
+        }
+
+        waiting.tick();
+        if waiting.check_win_lose() != GameResult::Playing {
+            break;
+        }
+    }
+
+    best
+}
+
+// every normal ball's position, then a grid over the arena
+fn candidates(game: &GameState, grid_step: f64) -> Vec<Point> {
+    let mut balls: Vec<_> = game
+        .objects
+        .values()
+        .filter(|ball| ball.ball_state == Normal)
+        .collect();
+    balls.sort_by_key(|ball| ball.id);
+    let mut points: Vec<Point> = balls.iter().map(|ball| ball.pos).collect();
+
+    let cols = (game.rect.w / grid_step).floor() as usize;
+    let rows = (game.rect.h / grid_step).floor() as usize;
+    for row in 0..rows {
+        for col in 0..cols {
+            points.push(Point {
+                x: game.rect.x + (col as f64 + 0.5) * grid_step,
+                y: game.rect.y + (row as f64 + 0.5) * grid_step,
+            });
+        }
+    }
+    points
+}
+
+fn is_capturing(game: &GameState) -> bool {
+    game.objects
+        .values()
+        .any(|ball| ball.ball_state == Expanding || ball.ball_state == Shrinking)
+}
+
+fn captures(game: &GameState, point: Point, horizon: usize) -> usize {
+    captured_by_click(game, point, horizon).len()
+}
+
+// Clicks on a copy of the game and runs it until every capture ball has burned out,
+// returning the ids of the balls that got captured, in id order.
+pub fn captured_by_click(game: &GameState, point: Point, horizon: usize) -> Vec<usize> {
+    let mut simulated = game.sandbox(BroadPhaseKind::SweepAndPrune);
+    simulated.apply_input(Input::Click {
+        x: point.x,
+        y: point.y,
+    });
+
+    let mut captured = vec![];
+    let mut free: Vec<usize> = game
+        .objects
+        .values()
+        .filter(|ball| ball.ball_state == Normal)
+        .map(|ball| ball.id)
+        .collect();
+
+    for _ in 0..horizon {
+        simulated.tick();
+        // captured balls shrink away and vanish later, so note them as they happen
+        free.retain(|id| match simulated.objects.get(id) {
+            Some(ball) if ball.is_captured => {
+                captured.push(*id);
+                false
+            }
+            _ => true,
+        });
+        if !is_capturing(&simulated) {
+            break;
+        }
+    }
+
+    captured.sort_unstable();
+    captured
+}
+
+// plays the shots the solver finds, one after another once the previous one burned out
+#[derive(Debug, Clone)]
+pub struct SolverBot {
+    config: SolverConfig,
+    // shot planned on the given frame
+    planned: Option<(usize, Point)>,
+}
+
+impl SolverBot {
+    pub fn new(config: SolverConfig) -> Self {
+        Self {
+            config,
+            planned: None,
+        }
+    }
+}
+
+impl Player for SolverBot {
+    fn name(&self) -> &str {
+        "solver"
+    }
+
+    fn act(&mut self, game: &GameState) -> Option<Point> {
+        if let Some((frame_id, point)) = self.planned {
+            if frame_id == game.frame_id {
+                self.planned = None;
+                return Some(point);
+            }
+            if frame_id > game.frame_id {
+                return None;
+            }
+            // the level was restarted or changed under us
+            self.planned = None;
+        }
+
+        if is_capturing(game) {
+            return None;
+        }
+
+        let shot = solve(game, &self.config)?;
+        if shot.delay == 0 {
+            return Some(shot.point);
+        }
+        self.planned = Some((game.frame_id + shot.delay, shot.point));
+        None
+    }
+}
@@ -0,0 +1,129 @@
+use crate::ball::{Color, WHITE};
+use crate::draw::{Draw, Stroke};
+use crate::geometry::{Point, Rect};
+use std::fmt::Write;
+
+// Builds a standalone SVG document out of draw calls. The document covers `rect` and has a
+// white background, like the canvas on the page.
+#[derive(Debug, Clone)]
+pub struct Svg {
+    rect: Rect,
+    alpha: f64,
+    elements: Vec<String>,
+}
+
+impl Svg {
+    pub fn new(rect: Rect) -> Self {
+        Self {
+            rect,
+            alpha: 1.0,
+            elements: vec![],
+        }
+    }
+
+    pub fn finish(&self) -> String {
+        let mut doc = String::new();
+        let Rect { x, y, w, h } = self.rect;
+        let _ = writeln!(
+            doc,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}" viewBox="{} {} {} {}">"#,
+            w, h, x, y, w, h
+        );
+        let _ = writeln!(
+            doc,
+            r#"<rect x="{}" y="{}" width="{}" height="{}" fill="{}"/>"#,
+            x, y, w, h, WHITE
+        );
+        for element in &self.elements {
+            doc.push_str(element);
+            doc.push('\n');
+        }
+        doc.push_str("</svg>\n");
+        doc
+    }
+
+    // opacity attribute, left out when fully opaque
+    fn opacity(&self) -> String {
+        if self.alpha < 1.0 {
+            format!(r#" opacity="{}""#, self.alpha)
+        } else {
+            String::new()
+        }
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+impl Draw for Svg {
+    fn clear(&mut self, rect: Rect) {
+        let r = self.rect;
+        let covers = rect.x <= r.x
+            && rect.y <= r.y
+            && rect.x + rect.w >= r.x + r.w
+            && rect.y + rect.h >= r.y + r.h;
+        if covers {
+            // nothing drawn so far can show through
+            self.elements.clear();
+        } else {
+            self.elements.push(format!(
+                r#"<rect x="{}" y="{}" width="{}" height="{}" fill="{}"/>"#,
+                rect.x, rect.y, rect.w, rect.h, WHITE
+            ));
+        }
+    }
+
+    fn alpha(&mut self, alpha: f64) {
+        self.alpha = alpha;
+    }
+
+    fn circle(&mut self, center: Point, radius: f64, fill: Option<Color>, stroke: Option<Stroke>) {
+        let fill = match fill {
+            Some(color) => color.to_string(),
+            None => "none".to_string(),
+        };
+        let stroke = match stroke {
+            Some(stroke) => format!(
+                r#" stroke="{}" stroke-width="{}""#,
+                stroke.color, stroke.width
+            ),
+            None => String::new(),
+        };
+        self.elements.push(format!(
+            r#"<circle cx="{}" cy="{}" r="{}" fill="{}"{}{}/>"#,
+            center.x,
+            center.y,
+            radius,
+            fill,
+            stroke,
+            self.opacity()
+        ));
+    }
+
+    fn rect(&mut self, rect: Rect, color: Color) {
+        self.elements.push(format!(
+            r#"<rect x="{}" y="{}" width="{}" height="{}" fill="none" stroke="{}"{}/>"#,
+            rect.x,
+            rect.y,
+            rect.w,
+            rect.h,
+            color,
+            self.opacity()
+        ));
+    }
+
+    fn text(&mut self, x: f64, y: f64, text: &str, color: Color) {
+        self.elements.push(format!(
+            r#"<text x="{}" y="{}" font-family="Verdana" font-size="14" fill="{}"{}>{}</text>"#,
+            x,
+            y,
+            color,
+            self.opacity(),
+            escape(text)
+        ));
+    }
+}
@@ -0,0 +1,42 @@
+use crate::constants::TICKS_PER_SECOND;
+
+// longest gap between two display frames that is caught up on, in seconds. After the
+// tab was in the background the game goes on where it was instead of fast-forwarding.
+pub const MAX_FRAME_TIME: f64 = 0.25;
+
+// Turns display frame times into a number of fixed-length simulation ticks, so the game
+// runs at the same speed on a 60 Hz and a 144 Hz screen. Time left over that isn't a
+// whole tick yet is carried to the next frame.
+#[derive(Debug, Clone, Default)]
+pub struct FixedStep {
+    last_frame: Option<f64>,
+    // time owed to the simulation, in ticks
+    pending: f64,
+}
+
+impl FixedStep {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Takes the time of a display frame in milliseconds, like the timestamp
+    // `requestAnimationFrame` passes, and returns how many ticks to run for it.
+    // The first frame only starts the clock.
+    pub fn advance(&mut self, now: f64) -> usize {
+        let elapsed = match self.last_frame {
+            Some(last) => ((now - last) / 1000.0).clamp(0.0, MAX_FRAME_TIME),
+            None => 0.0,
+        };
+        self.last_frame = Some(now);
+
+        self.pending += elapsed * TICKS_PER_SECOND;
+        let ticks = self.pending.floor();
+        self.pending -= ticks;
+        ticks as usize
+    }
+
+    // how far the display frame is between the last tick and the next one, 0 to 1
+    pub fn alpha(&self) -> f64 {
+        self.pending
+    }
+}
@@ -1,6 +1,6 @@
 use wasm_bindgen::closure::Closure;
 use wasm_bindgen::JsCast;
-use web_sys::{CanvasRenderingContext2d, console, HtmlCanvasElement};
+use web_sys::{CanvasRenderingContext2d, HtmlCanvasElement};
 
 pub fn set_panic_hook() {
     // When the `console_error_panic_hook` feature is enabled, we can call the
@@ -29,22 +29,16 @@ pub fn get_context() -> CanvasRenderingContext2d {
         .dyn_into::<HtmlCanvasElement>()
         .map_err(|_| ())
         .unwrap();
-    let context = canvas
+    canvas
         .get_context("2d")
         .unwrap()
         .unwrap()
         .dyn_into::<CanvasRenderingContext2d>()
-        .unwrap();
-    context
+        .unwrap()
 }
 
 
 pub fn get_debug_context() -> CanvasRenderingContext2d {
-    let win = window();
-
-    //get DPI
-    let dpi = win.device_pixel_ratio();
-
     let document = document();
     let canvas = document.get_element_by_id("canvas_debug").unwrap();
     let canvas: HtmlCanvasElement = canvas
@@ -52,19 +46,12 @@ pub fn get_debug_context() -> CanvasRenderingContext2d {
         .map_err(|_| ())
         .unwrap();
 
-    //let stil = win.get_computed_style(&canvas);
-
-    // let style_height = stil
-    // get_property_value("height");
-    //canvas.set_attribute("height", style_height * dpi);
-
-    let context = canvas
+    canvas
         .get_context("2d")
         .unwrap()
         .unwrap()
         .dyn_into::<CanvasRenderingContext2d>()
-        .unwrap();
-    context
+        .unwrap()
 }
 
 
@@ -72,7 +59,7 @@ pub fn window() -> web_sys::Window {
     web_sys::window().expect("no global `window` exists")
 }
 
-pub fn request_animation_frame(f: &Closure<dyn FnMut()>) {
+pub fn request_animation_frame(f: &Closure<dyn FnMut(f64)>) {
     window()
         .request_animation_frame(f.as_ref().unchecked_ref())
         .expect("should register `requestAnimationFrame` OK");
@@ -0,0 +1,63 @@
+use crate::geometry::{Point, Rect};
+
+// How the game's fixed coordinates map onto a canvas that is shown at some size in CSS
+// pixels on a screen with some device pixel ratio. The game always works in arena units,
+// only drawing and pointer events go through the viewport.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Viewport {
+    pub arena: Rect,
+    // size the canvas is displayed at, in CSS pixels
+    pub css_width: f64,
+    pub css_height: f64,
+    // device pixels per CSS pixel, `window.devicePixelRatio`
+    pub pixel_ratio: f64,
+}
+
+impl Viewport {
+    pub fn new(arena: Rect, css_width: f64, css_height: f64, pixel_ratio: f64) -> Self {
+        Self {
+            arena,
+            css_width: css_width.max(1.0),
+            css_height: css_height.max(1.0),
+            pixel_ratio: if pixel_ratio > 0.0 { pixel_ratio } else { 1.0 },
+        }
+    }
+
+    // The largest size with the arena's aspect ratio that fits into the container. A container
+    // without a height, like a plain block on the page, only limits the width.
+    pub fn fit(arena: Rect, container_width: f64, container_height: f64, pixel_ratio: f64) -> Self {
+        let mut scale = container_width / arena.w;
+        if container_height > 0.0 {
+            scale = scale.min(container_height / arena.h);
+        }
+        Self::new(arena, arena.w * scale, arena.h * scale, pixel_ratio)
+    }
+
+    // canvas width and height attributes, one backing pixel per device pixel
+    pub fn backing_size(&self) -> (u32, u32) {
+        (
+            (self.css_width * self.pixel_ratio).round().max(1.0) as u32,
+            (self.css_height * self.pixel_ratio).round().max(1.0) as u32,
+        )
+    }
+
+    // Backing pixels per arena unit horizontally and vertically, for the context transform.
+    // The arena's corner is at the canvas origin.
+    pub fn scale(&self) -> (f64, f64) {
+        let (width, height) = self.backing_size();
+        (width as f64 / self.arena.w, height as f64 / self.arena.h)
+    }
+
+    // a point in CSS pixels from the top left corner of the canvas content, in arena units
+    pub fn to_game(&self, x: f64, y: f64) -> Point {
+        Point {
+            x: self.arena.x + x * self.arena.w / self.css_width,
+            y: self.arena.y + y * self.arena.h / self.css_height,
+        }
+    }
+
+    pub fn contains(&self, point: Point) -> bool {
+        let Rect { x, y, w, h } = self.arena;
+        point.x >= x && point.x <= x + w && point.y >= y && point.y <= y + h
+    }
+}
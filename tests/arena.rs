@@ -0,0 +1,51 @@
+//! Games in arenas of other sizes than the default 640x480.
+
+use circles::logic::GameState;
+use circles::replay::{Playback, Replay};
+
+fn assert_inside(game: &GameState) {
+    let rect = game.rect;
+    for ball in game.objects.values() {
+        let p = ball.pos;
+        assert!(
+            p.x >= rect.x && p.x <= rect.x + rect.w && p.y >= rect.y && p.y <= rect.y + rect.h,
+            "ball {} at ({}, {}) is outside the {}x{} arena",
+            ball.id,
+            p.x,
+            p.y,
+            rect.w,
+            rect.h
+        );
+    }
+}
+
+#[test]
+fn balls_stay_inside_odd_arenas() {
+    for &(width, height) in &[(300, 900), (1200, 200), (64, 64)] {
+        for seed in 0..5 {
+            let mut game = GameState::with_seed(width, height, seed);
+            assert_eq!(game.rect.w, width as f64);
+            assert_eq!(game.rect.h, height as f64);
+            assert_inside(&game);
+            for _ in 0..600 {
+                game.advance();
+                assert_inside(&game);
+            }
+        }
+    }
+}
+
+#[test]
+fn replay_records_arena() {
+    let game = GameState::with_seed(1200, 200, 7);
+    assert_eq!(game.replay, Replay::with_arena(7, 1200, 200));
+
+    let text = game.replay.to_string();
+    assert!(text.contains("arena 1200 200\n"), "{}", text);
+    let parsed: Replay = text.parse().unwrap();
+    assert_eq!(parsed, game.replay);
+
+    let replayed = Playback::new(parsed).new_game();
+    assert_eq!(replayed.rect, game.rect);
+    assert_eq!(replayed.objects, game.objects);
+}
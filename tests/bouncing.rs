@@ -0,0 +1,101 @@
+//! Elastic ball-to-ball bouncing.
+
+use circles::ball::BallState::Normal;
+use circles::ball::{resolve_collision, Ball, BallPair, WHITE_BALL};
+use circles::constants::{HEIGHT, TICKS_PER_SECOND, WIDTH};
+use circles::geometry::Point;
+use circles::logic::GameState;
+
+fn ball(x: f64, y: f64, vx: f64, vy: f64, radius: f64) -> Ball {
+    let mut ball = Ball::new(
+        0,
+        Point { x, y },
+        Point { x: vx, y: vy },
+        WHITE_BALL,
+        Normal,
+    );
+    ball.radius = radius;
+    ball.next_radius = radius;
+    ball
+}
+
+fn momentum(pair: &BallPair) -> (f64, f64) {
+    let m1 = pair.first.radius * pair.first.radius;
+    let m2 = pair.second.radius * pair.second.radius;
+    (
+        m1 * pair.first.next_velocity.x + m2 * pair.second.next_velocity.x,
+        m1 * pair.first.next_velocity.y + m2 * pair.second.next_velocity.y,
+    )
+}
+
+fn energy(pair: &BallPair) -> f64 {
+    let speed_sq = |v: Point| v.x * v.x + v.y * v.y;
+    0.5 * pair.first.radius * pair.first.radius * speed_sq(pair.first.next_velocity)
+        + 0.5 * pair.second.radius * pair.second.radius * speed_sq(pair.second.next_velocity)
+}
+
+#[test]
+fn equal_balls_head_on_swap_velocities() {
+    let pair = resolve_collision(BallPair {
+        first: ball(100.0, 100.0, 2.0, 0.0, 10.0),
+        second: ball(119.0, 100.0, -1.0, 0.0, 10.0),
+    });
+
+    assert_eq!(pair.first.next_velocity, Point { x: -1.0, y: 0.0 });
+    assert_eq!(pair.second.next_velocity, Point { x: 2.0, y: 0.0 });
+}
+
+#[test]
+fn oblique_collision_conserves_momentum_and_energy() {
+    let before = BallPair {
+        first: ball(100.0, 100.0, 3.0, 1.0, 15.0),
+        second: ball(115.0, 108.0, -1.0, -2.0, 8.0),
+    };
+    let after = resolve_collision(before);
+
+    let (px, py) = momentum(&before);
+    let (qx, qy) = momentum(&after);
+    assert!((px - qx).abs() < 1e-9 && (py - qy).abs() < 1e-9);
+    assert!((energy(&before) - energy(&after)).abs() < 1e-9);
+    assert_ne!(before.first.next_velocity, after.first.next_velocity);
+}
+
+#[test]
+fn separating_balls_are_left_alone() {
+    let before = BallPair {
+        first: ball(100.0, 100.0, -1.0, 0.0, 10.0),
+        second: ball(115.0, 100.0, 1.0, 0.0, 10.0),
+    };
+
+    assert_eq!(resolve_collision(before), before);
+}
+
+#[test]
+fn normal_balls_bounce_only_in_bouncing_mode() {
+    for bouncing in [false, true] {
+        let mut game = GameState::with_seed(WIDTH, HEIGHT, 1);
+        game.mode.bouncing = bouncing;
+        game.objects.clear();
+
+        // 2 units a tick towards each other
+        let speed = 2.0 * TICKS_PER_SECOND;
+        let mut left = ball(100.0, 100.0, speed, 0.0, 10.0);
+        let mut right = ball(140.0, 100.0, -speed, 0.0, 10.0);
+        game.insert_object(&mut left);
+        game.insert_object(&mut right);
+
+        for _ in 0..20 {
+            game.tick();
+        }
+
+        let left = game.objects[&left.id];
+        let right = game.objects[&right.id];
+        if bouncing {
+            assert!(left.velocity.x < 0.0 && right.velocity.x > 0.0);
+            assert!(left.pos.x < right.pos.x);
+        } else {
+            assert!(left.velocity.x > 0.0 && right.velocity.x < 0.0);
+            assert!(left.pos.x > right.pos.x);
+        }
+    }
+}
@@ -0,0 +1,263 @@
+//! Every broad phase backend finds the same collisions.
+
+use circles::ball::{calc_moment_of_collision, Ball, BallPair, BallPairIds, BallType, RED_BALL};
+use circles::broadphase::BroadPhaseKind;
+use circles::constants::{HEIGHT, WIDTH};
+use circles::geometry::{Point, Rect};
+use circles::levels::LevelPack;
+use circles::logic::GameState;
+use circles::random::Random;
+use std::collections::{HashMap, HashSet};
+
+const ARENA: Rect = Rect {
+    x: 0.0,
+    y: 0.0,
+    w: WIDTH as f64,
+    h: HEIGHT as f64,
+};
+
+fn spawn(seed: u64, count: usize) -> HashMap<usize, Ball> {
+    let mut rng = Random::new(seed);
+    (1..=count)
+        .map(|id| {
+            let ball_type = BallType::random_ball_type(&mut rng);
+            (
+                id,
+                Ball::random_ball(&mut rng, id, WIDTH, HEIGHT, ball_type),
+            )
+        })
+        .collect()
+}
+
+// boxes touching along an edge count too, their balls may be touching
+fn overlap(a: Rect, b: Rect) -> bool {
+    a.x <= b.x + b.w && b.x <= a.x + a.w && a.y <= b.y + b.h && b.y <= a.y + a.h
+}
+
+fn ordered(pair: BallPairIds) -> (usize, usize) {
+    (pair.first.min(pair.second), pair.first.max(pair.second))
+}
+
+#[test]
+fn parses_names() {
+    for kind in BroadPhaseKind::ALL {
+        assert_eq!(kind.to_string().parse::<BroadPhaseKind>(), Ok(kind));
+    }
+    assert!("octree".parse::<BroadPhaseKind>().is_err());
+}
+
+#[test]
+fn candidates_cover_overlapping_bounding_boxes() {
+    for seed in 0..3 {
+        let mut objects = spawn(seed, 200);
+
+        for kind in BroadPhaseKind::ALL {
+            let mut broad_phase = kind.create(ARENA);
+
+            // a few frames, so backends that keep state between updates get exercised too
+            for _ in 0..10 {
+                for ball in objects.values_mut() {
+                    ball.tick(ARENA);
+                }
+                broad_phase.update(ARENA, &objects);
+
+                let candidates: Vec<(usize, usize)> =
+                    broad_phase.candidates().into_iter().map(ordered).collect();
+                let unique: HashSet<(usize, usize)> = candidates.iter().copied().collect();
+                assert_eq!(unique.len(), candidates.len(), "{} repeats pairs", kind);
+
+                for first in objects.values() {
+                    for second in objects.values() {
+                        if first.id < second.id
+                            && overlap(first.bounding_rect_swept(), second.bounding_rect_swept())
+                        {
+                            assert!(
+                                unique.contains(&(first.id, second.id)),
+                                "{} missed {} and {}",
+                                kind,
+                                first.id,
+                                second.id
+                            );
+                        }
+                    }
+                }
+
+                for ball in objects.values_mut() {
+                    ball.apply_tick_changes();
+                }
+            }
+        }
+    }
+}
+
+// The scene `cargo bench --bench broadphase` times at 1 000 balls: every backend against a
+// scan of all pairs with the swept narrow phase, none of them may miss a collision it finds.
+#[test]
+fn every_backend_finds_every_brute_force_collision() {
+    let json = r#"{ "name": "bench", "arena": { "width": 640, "height": 480 },
+                    "levels": [{ "shots": 1, "balls": 1000, "capture": 1 }] }"#;
+    let pack = LevelPack::from_json(json).unwrap();
+    let mut objects = GameState::from_level_pack(&pack, 1).objects;
+    let mut backends: Vec<_> = BroadPhaseKind::ALL
+        .iter()
+        .map(|kind| (*kind, kind.create(ARENA)))
+        .collect();
+
+    for _ in 0..3 {
+        // the same steps `tick` takes before its narrow phase
+        for ball in objects.values_mut() {
+            ball.tick(ARENA);
+        }
+
+        let balls: Vec<&Ball> = objects.values().collect();
+        let mut collisions = vec![];
+        for (i, first) in balls.iter().enumerate() {
+            for second in &balls[i + 1..] {
+                let pair = BallPair {
+                    first: **first,
+                    second: **second,
+                };
+                if pair.is_collision() || calc_moment_of_collision(first, second).is_some() {
+                    collisions.push(ordered(BallPairIds {
+                        first: first.id,
+                        second: second.id,
+                    }));
+                }
+            }
+        }
+        assert!(!collisions.is_empty());
+
+        for (kind, broad_phase) in backends.iter_mut() {
+            broad_phase.update(ARENA, &objects);
+            let candidates: HashSet<(usize, usize)> =
+                broad_phase.candidates().into_iter().map(ordered).collect();
+            let missed: Vec<_> = collisions
+                .iter()
+                .filter(|pair| !candidates.contains(pair))
+                .collect();
+            assert!(missed.is_empty(), "{} missed {:?}", kind, missed);
+        }
+
+        for ball in objects.values_mut() {
+            ball.apply_tick_changes();
+        }
+    }
+}
+
+// Two balls touching exactly on a quadtree split line, x = 180 is between the cells 160..180
+// and 180..200. The quadtree used to put them in those sibling cells and never pair them.
+#[test]
+fn balls_touching_on_a_split_line() {
+    let mut rng = Random::new(1);
+    let mut objects = HashMap::new();
+    for (id, x) in [(1, 175.0), (2, 185.0)] {
+        let mut ball = Ball::random_ball(&mut rng, id, WIDTH, HEIGHT, RED_BALL);
+        ball.pos = Point { x, y: 202.0 };
+        ball.next_position = ball.pos;
+        ball.radius = 5.0;
+        ball.next_radius = 5.0;
+        objects.insert(id, ball);
+    }
+    let pair = BallPair {
+        first: objects[&1],
+        second: objects[&2],
+    };
+    assert!(pair.is_collision());
+
+    for kind in BroadPhaseKind::ALL {
+        let mut broad_phase = kind.create(ARENA);
+        broad_phase.update(ARENA, &objects);
+        let candidates: Vec<(usize, usize)> =
+            broad_phase.candidates().into_iter().map(ordered).collect();
+        assert_eq!(candidates, vec![(1, 2)], "{}", kind);
+    }
+}
+
+// A shrinking ball overlaps its neighbour at the start of the tick, where the swept narrow
+// phase begins, but not at the end. Boxes only around the next radius missed the pair.
+#[test]
+fn shrinking_ball_keeps_its_start_radius() {
+    let mut rng = Random::new(2);
+    let mut objects = HashMap::new();
+    for (id, x, radius, next_radius) in [(1, 100.0, 20.0, 10.0), (2, 124.0, 5.0, 5.0)] {
+        let mut ball = Ball::random_ball(&mut rng, id, WIDTH, HEIGHT, RED_BALL);
+        ball.pos = Point { x, y: 100.0 };
+        ball.next_position = ball.pos;
+        ball.radius = radius;
+        ball.next_radius = next_radius;
+        objects.insert(id, ball);
+    }
+    assert_eq!(
+        calc_moment_of_collision(&objects[&1], &objects[&2]),
+        Some(0.0)
+    );
+
+    for kind in BroadPhaseKind::ALL {
+        let mut broad_phase = kind.create(ARENA);
+        broad_phase.update(ARENA, &objects);
+        let candidates: Vec<(usize, usize)> =
+            broad_phase.candidates().into_iter().map(ordered).collect();
+        assert_eq!(candidates, vec![(1, 2)], "{}", kind);
+    }
+}
+
+// guards against the partitioning degrading into a pair scan, `cargo bench --bench broadphase`
+// has the timings
+#[test]
+fn partitioning_prunes_most_pairs() {
+    let objects = spawn(5, 1_000);
+    let all_pairs = objects.len() * (objects.len() - 1) / 2;
+
+    for kind in BroadPhaseKind::ALL {
+        let mut broad_phase = kind.create(ARENA);
+        broad_phase.update(ARENA, &objects);
+        let candidates = broad_phase.candidates().len();
+
+        if kind == BroadPhaseKind::BruteForce {
+            assert_eq!(candidates, all_pairs);
+        } else {
+            assert!(
+                candidates < all_pairs / 3,
+                "{} has {} candidates out of {} pairs",
+                kind,
+                candidates,
+                all_pairs
+            );
+        }
+    }
+}
+
+#[test]
+fn same_game_with_every_backend() {
+    let play = |kind: BroadPhaseKind| {
+        let mut game = GameState::with_seed(WIDTH, HEIGHT, 11);
+        game.mode.bouncing = true;
+        game.use_broad_phase(kind);
+        game.next_level();
+        game.next_level();
+
+        for frame in 0..400 {
+            if frame == 50 {
+                game.create_capture_ball(WIDTH as f64 / 2.0, HEIGHT as f64 / 2.0);
+            }
+            // removed balls have to leave the backends too
+            if frame == 200 {
+                game.restart();
+            }
+            game.tick();
+        }
+
+        let mut balls: Vec<(usize, f64, f64)> = game
+            .objects
+            .values()
+            .map(|ball| (ball.id, ball.pos.x, ball.pos.y))
+            .collect();
+        balls.sort_by_key(|(id, _, _)| *id);
+        (game.captured, balls)
+    };
+
+    let reference = play(BroadPhaseKind::BruteForce);
+    for kind in BroadPhaseKind::ALL {
+        assert_eq!(play(kind), reference, "{} plays differently", kind);
+    }
+}
@@ -0,0 +1,123 @@
+//! The `circles-sim` command-line simulator.
+
+use circles::constants::{HEIGHT, WIDTH};
+use circles::logic::{GameResult, GameState};
+use circles::replay::Input;
+use std::process::{Command, Output};
+
+fn sim(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_circles-sim"))
+        .args(args)
+        .output()
+        .expect("circles-sim should run")
+}
+
+fn json(args: &[&str]) -> serde_json::Value {
+    let mut args = args.to_vec();
+    args.extend(["--format", "json"]);
+    let output = sim(&args);
+    assert!(output.status.success(), "{:?}", output);
+    serde_json::from_slice(&output.stdout).expect("output should be json")
+}
+
+#[test]
+fn greedy_bot_plays_a_level() {
+    let report = json(&["--seed", "3", "--level", "1"]);
+
+    assert_eq!(report["seed"], 3);
+    assert_eq!(report["level"], 1);
+    assert_eq!(report["player"], "greedy");
+    assert_eq!(report["result"], "won");
+    assert_eq!(report["shots_used"], 1);
+    assert!(report["captured"].as_u64() >= report["required"].as_u64());
+}
+
+#[test]
+fn same_arguments_same_report() {
+    let args = ["--seed", "8", "--level", "4", "--bot", "random"];
+    assert_eq!(json(&args), json(&args));
+}
+
+#[test]
+fn stops_after_ticks() {
+    let report = json(&["--bot", "none", "--ticks", "50"]);
+    assert_eq!(report["result"], "undecided");
+    assert_eq!(report["frames"], 50);
+    assert_eq!(report["shots_used"], 0);
+}
+
+#[test]
+fn text_report() {
+    let output = sim(&["--seed", "3", "--broad-phase", "sweep-and-prune"]);
+    let text = String::from_utf8(output.stdout).unwrap();
+    assert!(text.starts_with("seed 3 level 1 player greedy: won, captured "));
+}
+
+#[test]
+fn plays_replays() {
+    let mut game = GameState::with_seed(WIDTH, HEIGHT, 12);
+    for _ in 0..20 {
+        game.advance();
+    }
+    game.apply_input(Input::Click { x: 320.0, y: 240.0 });
+    while game.result == GameResult::Playing {
+        game.advance();
+    }
+
+    let path = std::env::temp_dir().join("circles-sim-test.replay");
+    std::fs::write(&path, game.replay.to_string()).unwrap();
+    let report = json(&["--replay", path.to_str().unwrap()]);
+
+    assert_eq!(report["player"], "replay");
+    assert_eq!(report["seed"], 12);
+    assert_eq!(report["captured"], game.captured);
+    assert_eq!(report["frames"], game.frame_id);
+}
+
+#[test]
+fn bad_arguments_exit_with_2() {
+    for args in [
+        vec!["--bot", "clever"],
+        vec!["--level", "99"],
+        vec!["--seed"],
+        vec!["--ticks", "many"],
+        vec!["--replay", "/does/not/exist"],
+        // a replay brings its own game
+        vec!["--replay", "bug.replay", "--seed", "3"],
+        vec!["--levels", "pack.json", "--replay", "bug.replay"],
+        vec!["--replay", "bug.replay", "--bot", "random"],
+    ] {
+        let output = sim(&args);
+        assert_eq!(output.status.code(), Some(2), "{:?}", args);
+        assert!(!output.stderr.is_empty());
+    }
+}
+
+#[test]
+fn writes_the_last_frame_as_svg() {
+    let path = std::env::temp_dir().join(format!("circles-sim-{}.svg", std::process::id()));
+    let file = path.to_str().unwrap();
+    let output = sim(&[
+        "--seed", "2", "--bot", "none", "--ticks", "10", "--svg", file,
+    ]);
+    assert!(output.status.success(), "{:?}", output);
+
+    let svg = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert!(svg.starts_with("<svg"));
+    assert!(svg.contains("<circle"));
+}
+
+#[test]
+fn writes_the_last_frame_as_png() {
+    let path = std::env::temp_dir().join(format!("circles-sim-{}.png", std::process::id()));
+    let file = path.to_str().unwrap();
+    let output = sim(&[
+        "--seed", "2", "--bot", "none", "--ticks", "10", "--png", file,
+    ]);
+    assert!(output.status.success(), "{:?}", output);
+
+    let png = std::fs::read(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert!(png.starts_with(b"\x89PNG"));
+}
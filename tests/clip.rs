@@ -0,0 +1,155 @@
+//! Replays exported as animations.
+
+use circles::clip::{write_apng, ClipFrames, ClipOptions};
+use circles::constants::{HEIGHT, TICKS_PER_SECOND, WIDTH};
+use circles::logic::{ChangeState, GameResult, GameState};
+use circles::player::{play_level, GreedyBot};
+use circles::raster::Raster;
+use circles::rendering::Renderer;
+use circles::replay::{Input, Replay};
+use std::process::Command;
+
+// greedy bot winning level 1, then going on to the next level
+fn replay() -> (Replay, GameState) {
+    let mut game = GameState::with_seed(WIDTH, HEIGHT, 3);
+    play_level(&mut GreedyBot, &mut game, 20_000);
+    assert_eq!(game.result, GameResult::Won);
+    game.apply_input(Input::Change(ChangeState::NextLevel));
+    (game.replay.clone(), game)
+}
+
+fn options() -> ClipOptions {
+    ClipOptions {
+        every: 3,
+        hold: 30,
+        max_ticks: 2_000,
+    }
+}
+
+#[test]
+fn ends_on_the_result_screen() {
+    let mut game = GameState::with_seed(WIDTH, HEIGHT, 3);
+    play_level(&mut GreedyBot, &mut game, 20_000);
+
+    let frames: Vec<_> = ClipFrames::new(game.replay.clone(), options()).collect();
+    let last = frames.last().unwrap();
+
+    let mut won = Raster::new(game.rect);
+    Renderer::new(&game).render_won(&mut won);
+    assert_eq!(last.raster, won);
+    // the sample the level is won in still has a tick of play
+    assert!(last.ticks + options().every > options().hold);
+    assert!(frames.len() > 10);
+}
+
+#[test]
+fn frames_cover_every_tick_once() {
+    let (replay, _) = replay();
+    let frames: Vec<_> = ClipFrames::new(replay, options()).collect();
+
+    let mut tick = 0;
+    for pair in frames.windows(2) {
+        assert_ne!(
+            pair[0].raster, pair[1].raster,
+            "same frames should be merged"
+        );
+    }
+    for frame in &frames {
+        assert_eq!(frame.tick, tick);
+        tick += frame.ticks;
+    }
+}
+
+#[test]
+fn stops_once_the_replay_played_out() {
+    let options = ClipOptions {
+        every: 1,
+        hold: 40,
+        max_ticks: 1_000,
+    };
+    let frames: Vec<_> = ClipFrames::new(Replay::new(9), options).collect();
+    assert_eq!(frames.iter().map(|frame| frame.ticks).sum::<usize>(), 40);
+}
+
+#[test]
+fn plays_on_after_the_next_level() {
+    let (replay, game) = replay();
+    let mut clip = ClipFrames::new(replay, options());
+    clip.by_ref().for_each(drop);
+
+    assert_eq!(clip.game().level_id, game.level_id);
+    assert_eq!(clip.game().level_id, 2);
+}
+
+#[test]
+fn stops_after_max_ticks() {
+    let options = ClipOptions {
+        every: 1,
+        hold: 1_000,
+        max_ticks: 100,
+    };
+    let frames: Vec<_> = ClipFrames::new(Replay::new(9), options).collect();
+    assert_eq!(frames.iter().map(|frame| frame.ticks).sum::<usize>(), 100);
+}
+
+#[test]
+fn apng_has_every_frame() {
+    let (replay, _) = replay();
+    // encoding is slow in debug builds, keep the frames few
+    let options = ClipOptions {
+        every: 15,
+        ..options()
+    };
+    let frames: Vec<_> = ClipFrames::new(replay.clone(), options).collect();
+
+    let mut bytes = vec![];
+    let count = write_apng(&replay, options, &mut bytes).unwrap();
+    assert_eq!(count, frames.len());
+
+    let mut reader = png::Decoder::new(bytes.as_slice()).read_info().unwrap();
+    let control = reader.info().animation_control.unwrap();
+    assert_eq!(control.num_frames as usize, frames.len());
+    assert_eq!(control.num_plays, 0);
+
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    for frame in &frames {
+        reader.next_frame(&mut buffer).unwrap();
+        let control = reader.info().frame_control.unwrap();
+        assert_eq!(control.delay_num as usize, frame.ticks);
+        assert_eq!(control.delay_den, TICKS_PER_SECOND as u16);
+        assert_eq!(buffer, frame.raster.rgb());
+    }
+}
+
+#[test]
+fn command_line_tool_writes_files() {
+    let (replay, _) = replay();
+    let dir = std::env::temp_dir().join(format!("circles-clip-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let replay_file = dir.join("level.replay");
+    std::fs::write(&replay_file, replay.to_string()).unwrap();
+    let apng = dir.join("level.png");
+    let frames = dir.join("frames");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_circles-clip"))
+        .arg(&replay_file)
+        .args(["--every", "15", "--hold", "10", "--apng"])
+        .arg(&apng)
+        .arg("--frames")
+        .arg(&frames)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+
+    let written = std::fs::read_dir(&frames).unwrap().count();
+    assert!(written > 1);
+    assert!(std::fs::read(&apng).unwrap().starts_with(b"\x89PNG"));
+    assert!(frames.join("frame-000000.png").exists());
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_circles-clip"))
+        .arg(&replay_file)
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(2));
+}
@@ -0,0 +1,150 @@
+//! Narrow phase: which overlapping balls get captured.
+
+use circles::ball::BallState::{Expanding, Normal};
+use circles::ball::{calc_moment_of_collision, Ball, BallPair, ACTIVE_BALL, WHITE_BALL};
+use circles::constants::{HEIGHT, TICKS_PER_SECOND, WIDTH};
+use circles::geometry::Point;
+use circles::logic::{GameState, NarrowPhase};
+
+fn ball(x: f64, y: f64, radius: f64) -> Ball {
+    let mut ball = Ball::new(
+        0,
+        Point { x, y },
+        Point { x: 0.0, y: 0.0 },
+        WHITE_BALL,
+        Normal,
+    );
+    ball.radius = radius;
+    ball.next_radius = radius;
+    ball
+}
+
+#[test]
+fn circles_touching_only_at_box_corners_do_not_collide() {
+    let pair = BallPair {
+        first: ball(100.0, 100.0, 10.0),
+        second: ball(115.0, 115.0, 10.0),
+    };
+
+    assert!(pair.is_collision_bb());
+    assert!(!pair.is_collision());
+}
+
+#[test]
+fn overlapping_circles_collide() {
+    // distance 19 < 10 + 10, but 19² > 10² + 10²
+    let pair = BallPair {
+        first: ball(100.0, 100.0, 10.0),
+        second: ball(119.0, 100.0, 10.0),
+    };
+
+    assert!(pair.is_collision());
+}
+
+// places an expanding active ball of radius 20 and a white ball of radius 15
+// diagonally `offset` apart, ticks once and reports whether the white one got captured
+fn is_captured(narrow_phase: NarrowPhase, offset: f64) -> bool {
+    let mut game = GameState::with_seed(WIDTH, HEIGHT, 1);
+    game.mode.narrow_phase = narrow_phase;
+    game.objects.clear();
+
+    let mut active = Ball::new(
+        0,
+        Point { x: 100.0, y: 100.0 },
+        Point { x: 0.0, y: 0.0 },
+        ACTIVE_BALL,
+        Expanding,
+    );
+    active.radius = 20.0;
+    active.is_captured = true;
+    game.insert_object(&mut active);
+
+    let mut target = ball(100.0 + offset, 100.0 + offset, 15.0);
+    game.insert_object(&mut target);
+
+    game.tick();
+    game.objects[&target.id].is_captured
+}
+
+#[test]
+fn diagonal_near_miss_is_not_captured_with_circles() {
+    assert!(is_captured(NarrowPhase::BoundingBox, 30.0));
+    assert!(!is_captured(NarrowPhase::Circle, 30.0));
+}
+
+#[test]
+fn diagonal_overlap_is_captured_with_circles() {
+    assert!(is_captured(NarrowPhase::Circle, 20.0));
+    assert!(is_captured(NarrowPhase::BoundingBox, 20.0));
+}
+
+#[test]
+fn time_of_impact_for_fast_ball_passing_through() {
+    let active = ball(100.0, 100.0, 10.0);
+    let mut fast = ball(70.0, 100.0, 5.0);
+    fast.next_position = Point { x: 130.0, y: 100.0 };
+
+    let pair = BallPair {
+        first: active,
+        second: fast,
+    };
+    assert!(!pair.is_collision());
+    assert_eq!(calc_moment_of_collision(&active, &fast), Some(0.25));
+    assert_eq!(calc_moment_of_collision(&fast, &active), Some(0.25));
+}
+
+#[test]
+fn time_of_impact_with_growing_radius() {
+    let mut growing = ball(100.0, 100.0, 10.0);
+    growing.next_radius = 30.0;
+    let still = ball(130.0, 100.0, 10.0);
+
+    assert_eq!(calc_moment_of_collision(&growing, &still), Some(0.5));
+}
+
+#[test]
+fn time_of_impact_misses() {
+    let mut passing = ball(70.0, 130.0, 5.0);
+    passing.next_position = Point { x: 130.0, y: 130.0 };
+    let still = ball(100.0, 100.0, 10.0);
+    assert_eq!(calc_moment_of_collision(&passing, &still), None);
+
+    let mut slow = ball(70.0, 100.0, 5.0);
+    slow.next_position = Point { x: 75.0, y: 100.0 };
+    assert_eq!(calc_moment_of_collision(&slow, &still), None);
+}
+
+#[test]
+fn fast_ball_does_not_tunnel_past_active_ball() {
+    let tunnels = |narrow_phase: NarrowPhase| {
+        let mut game = GameState::with_seed(WIDTH, HEIGHT, 1);
+        game.mode.narrow_phase = narrow_phase;
+        game.objects.clear();
+
+        let mut active = Ball::new(
+            0,
+            Point { x: 200.0, y: 200.0 },
+            Point { x: 0.0, y: 0.0 },
+            ACTIVE_BALL,
+            Expanding,
+        );
+        active.radius = 10.0;
+        active.is_captured = true;
+        game.insert_object(&mut active);
+
+        let mut fast = ball(170.0, 200.0, 5.0);
+        // 60 units a tick
+        fast.velocity = Point {
+            x: 60.0 * TICKS_PER_SECOND,
+            y: 0.0,
+        };
+        game.insert_object(&mut fast);
+
+        game.tick();
+        game.tick();
+        !game.objects[&fast.id].is_captured
+    };
+
+    assert!(tunnels(NarrowPhase::Circle));
+    assert!(!tunnels(NarrowPhase::Swept));
+}
@@ -0,0 +1,108 @@
+//! Gym-style environment for training agents.
+
+use circles::env::{Action, Env};
+use circles::logic::GameResult;
+
+fn positions(env: &Env) -> Vec<(usize, f64, f64)> {
+    let mut balls: Vec<(usize, f64, f64)> = env
+        .game()
+        .objects
+        .values()
+        .map(|ball| (ball.id, ball.pos.x, ball.pos.y))
+        .collect();
+    balls.sort_by_key(|(id, _, _)| *id);
+    balls
+}
+
+// clicks once and waits for the level to end, returning the total reward
+fn play(env: &mut Env, x: f64, y: f64) -> (f64, usize, GameResult) {
+    let mut total = 0.0;
+    let mut steps = 0;
+    let mut action = Action::Click { x, y };
+    loop {
+        let step = env.step(action);
+        action = Action::NoOp;
+        total += step.reward;
+        steps += 1;
+        if step.done {
+            return (total, steps, step.result);
+        }
+    }
+}
+
+#[test]
+fn reset_starts_the_requested_level() {
+    let mut env = Env::new(1);
+    let observation = env.reset(3, 4);
+
+    assert_eq!(observation.level_id, 4);
+    assert_eq!(observation.frame_id, 0);
+    assert_eq!(observation.captured, 0);
+    assert_eq!(observation.shots, env.game().level().max_shots);
+    assert_eq!(observation.balls.len(), env.game().level().num_of_balls);
+    assert!(observation
+        .balls
+        .windows(2)
+        .all(|pair| pair[0].id < pair[1].id));
+}
+
+#[test]
+fn reset_is_deterministic() {
+    let mut env = Env::new(2);
+    env.reset(9, 2);
+    let first = positions(&env);
+    let outcome = play(&mut env, 320.0, 240.0);
+
+    env.reset(9, 2);
+    assert_eq!(positions(&env), first);
+    assert_eq!(play(&mut env, 320.0, 240.0), outcome);
+}
+
+#[test]
+fn frame_skip_advances_frames() {
+    let mut env = Env::new(4);
+    env.reset(1, 1);
+
+    let step = env.step(Action::NoOp);
+    assert_eq!(step.observation.frame_id, 4);
+    assert_eq!(step.reward, 0.0);
+    assert!(!step.done);
+
+    env.set_frame_skip(1);
+    assert_eq!(env.step(Action::NoOp).observation.frame_id, 5);
+}
+
+#[test]
+fn reward_counts_captured_balls() {
+    let mut env = Env::new(1);
+    env.reset(5, 1);
+
+    // the capture ball itself is no reward
+    let step = env.step(Action::Click { x: 320.0, y: 240.0 });
+    assert_eq!(step.reward, 0.0);
+    assert_eq!(step.observation.shots, 0);
+
+    let (total, _, result) = play(&mut env, 0.0, 0.0);
+    assert_ne!(result, GameResult::Playing);
+
+    // the game counts the capture ball towards the goal
+    let captured = env.game().captured;
+    assert_eq!(total, (captured - 1) as f64);
+    assert_eq!(
+        result == GameResult::Won,
+        captured >= env.game().captured_required
+    );
+}
+
+#[test]
+fn clicks_after_the_level_ended_are_ignored() {
+    let mut env = Env::new(8);
+    env.reset(2, 1);
+    play(&mut env, 100.0, 100.0);
+
+    let frame_id = env.game().frame_id;
+    let step = env.step(Action::Click { x: 200.0, y: 200.0 });
+    assert!(step.done);
+    assert_eq!(step.reward, 0.0);
+    assert_eq!(step.observation.frame_id, frame_id);
+}
@@ -0,0 +1,132 @@
+//! Loading and validating level packs.
+
+use circles::levels::{LevelError, LevelPack, LevelPackError};
+use circles::logic::{ChangeState, GameState};
+use circles::replay::Input;
+
+const PACK: &str = r#"{
+  "name": "reds",
+  "arena": { "width": 800, "height": 600 },
+  "levels": [
+    { "shots": 2, "balls": 40, "capture": 10,
+      "ball_types": [{ "type": "RED_BALL", "weight": 3 }, { "type": "SILVER_BALL", "weight": 1 }] },
+    { "shots": 1, "balls": 5, "capture": 1 }
+  ]
+}"#;
+
+fn pack_with_level(level: &str) -> String {
+    format!(
+        r#"{{ "name": "test", "arena": {{ "width": 640, "height": 480 }}, "levels": [{}] }}"#,
+        level
+    )
+}
+
+#[test]
+fn classic_pack_matches_original_campaign() {
+    let pack = LevelPack::classic();
+
+    assert_eq!(pack.levels.len(), 18);
+    assert_eq!((pack.width, pack.height), (640, 480));
+    let first = &pack.levels[0];
+    assert_eq!(
+        (first.max_shots, first.num_of_balls, first.num_captured),
+        (1, 5, 2)
+    );
+    let tenth = &pack.levels[9];
+    assert_eq!(
+        (tenth.max_shots, tenth.num_of_balls, tenth.num_captured),
+        (1, 35, 27)
+    );
+}
+
+#[test]
+fn pack_controls_arena_shots_and_ball_types() {
+    let pack = LevelPack::from_json(PACK).unwrap();
+    let game = GameState::from_level_pack(&pack, 5);
+
+    assert_eq!((game.rect.w, game.rect.h), (800.0, 600.0));
+    assert_eq!(game.shots, 2);
+    assert_eq!(game.captured_required, 10);
+    assert_eq!(game.objects.len(), 40);
+    assert!(game
+        .objects
+        .values()
+        .all(|ball| ball.ball_type == "RED_BALL" || ball.ball_type == "SILVER_BALL"));
+    assert!(game
+        .objects
+        .values()
+        .any(|ball| ball.ball_type == "RED_BALL"));
+}
+
+#[test]
+fn malformed_packs_are_rejected() {
+    assert!(matches!(
+        LevelPack::from_json("{ \"name\": \"broken\""),
+        Err(LevelPackError::Parse(_))
+    ));
+    assert!(matches!(
+        LevelPack::from_json(&pack_with_level(
+            r#"{ "shots": 1, "balls": 5, "capture": 1, "speed": 3 }"#
+        )),
+        Err(LevelPackError::Parse(_))
+    ));
+    assert_eq!(
+        LevelPack::from_json(
+            r#"{ "name": "empty", "arena": { "width": 640, "height": 480 }, "levels": [] }"#
+        )
+        .unwrap_err(),
+        LevelPackError::NoLevels
+    );
+    assert_eq!(
+        LevelPack::from_json(
+            r#"{ "name": "flat", "arena": { "width": 640, "height": 0 }, "levels": [] }"#
+        )
+        .unwrap_err(),
+        LevelPackError::InvalidArena(640, 0)
+    );
+    assert_eq!(
+        LevelPack::from_json(&pack_with_level(
+            r#"{ "shots": 0, "balls": 5, "capture": 1 }"#
+        ))
+        .unwrap_err(),
+        LevelPackError::Level(1, LevelError::NoShots)
+    );
+    assert_eq!(
+        LevelPack::from_json(&pack_with_level(
+            r#"{ "shots": 1, "balls": 5, "capture": 7 }"#
+        ))
+        .unwrap_err(),
+        LevelPackError::Level(1, LevelError::CaptureTooHigh(7, 6))
+    );
+    assert_eq!(
+        LevelPack::from_json(&pack_with_level(
+            r#"{ "shots": 1, "balls": 5, "capture": 1, "ball_types": [{ "type": "PINK_BALL", "weight": 1 }] }"#
+        ))
+        .unwrap_err(),
+        LevelPackError::Level(1, LevelError::UnknownBallType("PINK_BALL".to_string()))
+    );
+    assert_eq!(
+        LevelPack::from_json(&pack_with_level(
+            r#"{ "shots": 1, "balls": 5, "capture": 1, "ball_types": [{ "type": "RED_BALL", "weight": 0 }] }"#
+        ))
+        .unwrap_err()
+        .to_string(),
+        "level 1: weight 0 of 'RED_BALL' should be positive"
+    );
+}
+
+#[test]
+fn campaign_starts_over_after_last_level() {
+    let pack = LevelPack::from_json(PACK).unwrap();
+    let mut game = GameState::from_level_pack(&pack, 3);
+    assert!(!game.is_last_level());
+
+    game.apply_input(Input::Change(ChangeState::NextLevel));
+    assert_eq!(game.level_id, 2);
+    assert!(game.is_last_level());
+
+    // used to panic on a level that doesn't exist
+    game.apply_input(Input::Change(ChangeState::NextLevel));
+    assert_eq!(game.level_id, 1);
+    assert_eq!(game.objects.len(), 40);
+}
@@ -0,0 +1,129 @@
+//! Observation encodings for ML agents.
+
+use circles::ball::BallType;
+use circles::constants::{HEIGHT, WIDTH};
+use circles::env::{Action, Env};
+use circles::logic::GameState;
+use circles::observation::{
+    ball_type_index, Encoding, BALL_FEATURES, BALL_TYPE_NAMES, ENCODING_VERSION, RASTER_CHANNELS,
+};
+
+fn game() -> GameState {
+    let mut game = GameState::with_seed(WIDTH, HEIGHT, 4);
+    game.start_level(10);
+    game
+}
+
+#[test]
+fn every_ball_type_has_an_index() {
+    for name in BALL_TYPE_NAMES.iter() {
+        assert!(
+            BallType::from_name(name).is_some() || *name == "ACTIVE_BALL",
+            "{}",
+            name
+        );
+    }
+    assert_eq!(ball_type_index("WHITE_BALL"), Some(0));
+    assert_eq!(ball_type_index("ACTIVE_BALL"), Some(18));
+    assert_eq!(ball_type_index("PINK_BALL"), None);
+}
+
+#[test]
+fn raw_lists_every_ball_by_id() {
+    let game = game();
+    let encoded = game.observe(Encoding::Raw);
+
+    assert_eq!(encoded.version, ENCODING_VERSION);
+    assert_eq!(encoded.shape, vec![game.objects.len(), BALL_FEATURES]);
+    assert_eq!(encoded.data.len(), game.objects.len() * BALL_FEATURES);
+
+    let first = &game.objects[&2];
+    let features = &encoded.data[..BALL_FEATURES];
+    assert_eq!(features[0], 1.0);
+    assert_eq!(features[1], (first.pos.x / WIDTH as f64) as f32);
+    assert_eq!(features[2], (first.pos.y / HEIGHT as f64) as f32);
+    assert_eq!(features[3], first.step().x as f32);
+    assert_eq!(features[4], first.step().y as f32);
+    assert_eq!(features[5], first.radius as f32);
+    assert_eq!(features[6], 0.0);
+    assert_eq!(
+        features[7],
+        ball_type_index(first.ball_type).unwrap() as f32
+    );
+}
+
+#[test]
+fn nearest_has_a_fixed_length() {
+    let game = game();
+    let center = game.rect.center();
+
+    let encoded = game.observe(Encoding::Nearest { count: 5 });
+    assert_eq!(encoded.shape, vec![5, BALL_FEATURES]);
+    assert_eq!(encoded.data.len(), 5 * BALL_FEATURES);
+
+    let distances: Vec<f32> = encoded
+        .data
+        .chunks(BALL_FEATURES)
+        .map(|ball| {
+            let dx = ball[1] * WIDTH as f32 - center.x as f32;
+            let dy = ball[2] * HEIGHT as f32 - center.y as f32;
+            dx * dx + dy * dy
+        })
+        .collect();
+    assert!(distances.windows(2).all(|pair| pair[0] <= pair[1] + 1e-3));
+
+    // padded with absent balls
+    let count = game.objects.len() + 3;
+    let encoded = game.observe(Encoding::Nearest { count });
+    assert_eq!(encoded.data.len(), count * BALL_FEATURES);
+    assert!(encoded.data[game.objects.len() * BALL_FEATURES..]
+        .iter()
+        .all(|value| *value == 0.0));
+}
+
+#[test]
+fn raster_marks_occupied_cells() {
+    let mut game = game();
+    let (cols, rows) = (32, 24);
+
+    let encoded = game.observe(Encoding::Raster { cols, rows });
+    assert_eq!(encoded.shape, vec![RASTER_CHANNELS, rows, cols]);
+    let (normal, active) = encoded.data.split_at(rows * cols);
+    assert!(normal.contains(&1.0));
+    assert!(active.iter().all(|cell| *cell == 0.0));
+
+    for ball in game.objects.values() {
+        let col = (ball.pos.x / WIDTH as f64 * cols as f64) as usize;
+        let row = (ball.pos.y / HEIGHT as f64 * rows as f64) as usize;
+        assert_eq!(normal[row * cols + col], 1.0);
+    }
+
+    game.create_capture_ball(5.0, 5.0);
+    let encoded = game.observe(Encoding::Raster { cols, rows });
+    assert_eq!(encoded.data[rows * cols], 1.0);
+}
+
+#[test]
+fn empty_raster_is_one_cell() {
+    let game = game();
+
+    let encoded = game.observe(Encoding::Raster { cols: 0, rows: 0 });
+    assert_eq!(encoded.shape, vec![RASTER_CHANNELS, 1, 1]);
+    assert_eq!(encoded.data, vec![1.0, 0.0]);
+
+    let encoded = game.observe(Encoding::Raster { cols: 4, rows: 0 });
+    assert_eq!(encoded.shape, vec![RASTER_CHANNELS, 1, 4]);
+    assert_eq!(encoded.data.len(), RASTER_CHANNELS * 4);
+}
+
+#[test]
+fn env_uses_its_encoding() {
+    let mut env = Env::new(1);
+    env.set_encoding(Encoding::Nearest { count: 10 });
+
+    let observation = env.reset(1, 3);
+    assert_eq!(observation.features.shape, vec![10, BALL_FEATURES]);
+
+    let step = env.step(Action::NoOp);
+    assert_eq!(step.observation.features.data.len(), 10 * BALL_FEATURES);
+}
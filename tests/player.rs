@@ -0,0 +1,91 @@
+//! Bots playing levels through the `Player` trait.
+
+use circles::constants::{FULL, HEIGHT, WIDTH};
+use circles::geometry::Point;
+use circles::logic::{GameResult, GameState};
+use circles::player::{play_level, GreedyBot, Player, RandomBot};
+use circles::replay::Input;
+
+fn level(seed: u64, level_id: usize) -> GameState {
+    let mut game = GameState::with_seed(WIDTH, HEIGHT, seed);
+    game.start_level(level_id);
+    game
+}
+
+fn wins(player: &mut dyn Player, level_id: usize, seeds: u64) -> usize {
+    (0..seeds)
+        .filter(|seed| {
+            let mut game = level(*seed, level_id);
+            play_level(player, &mut game, 20_000).result == GameResult::Won
+        })
+        .count()
+}
+
+#[test]
+fn greedy_beats_the_first_level() {
+    assert_eq!(wins(&mut GreedyBot, 1, 10), 10);
+}
+
+#[test]
+fn greedy_beats_random() {
+    let greedy: usize = (1..=3)
+        .map(|level_id| wins(&mut GreedyBot, level_id, 10))
+        .sum();
+    let random: usize = (1..=3)
+        .map(|level_id| wins(&mut RandomBot::new(level_id as u64), level_id, 10))
+        .sum();
+    assert!(greedy > random, "greedy {} random {}", greedy, random);
+}
+
+#[test]
+fn greedy_clicks_the_densest_spot() {
+    let mut game = level(2, 6);
+    let (point, covered) = GreedyBot::best_click(&game).unwrap();
+
+    let near = |center: Point| {
+        game.objects
+            .values()
+            .filter(|ball| {
+                let reach = FULL + ball.radius;
+                (ball.pos.x - center.x).powi(2) + (ball.pos.y - center.y).powi(2) <= reach * reach
+            })
+            .count()
+    };
+    assert_eq!(near(point), covered);
+    assert!(game.objects.values().all(|ball| near(ball.pos) <= covered));
+
+    // waits while its shot is still capturing
+    assert_eq!(GreedyBot.act(&game), Some(point));
+    game.apply_input(Input::Click { x: 1.0, y: 1.0 });
+    assert_eq!(GreedyBot.act(&game), None);
+}
+
+#[test]
+fn clicks_are_recorded() {
+    let mut game = level(4, 2);
+    let outcome = play_level(&mut RandomBot::with_chance(1, 0.5), &mut game, 20_000);
+
+    assert_ne!(outcome.result, GameResult::Playing);
+    assert_eq!(outcome.frames, game.frame_id);
+    assert_eq!(outcome.captured, game.captured);
+    assert_eq!(game.replay.events.len(), game.level().max_shots);
+}
+
+#[test]
+fn gives_up_after_max_frames() {
+    struct Idle;
+    impl Player for Idle {
+        fn name(&self) -> &str {
+            "idle"
+        }
+
+        fn act(&mut self, _game: &GameState) -> Option<Point> {
+            None
+        }
+    }
+
+    let mut game = level(1, 1);
+    let outcome = play_level(&mut Idle, &mut game, 100);
+    assert_eq!(outcome.result, GameResult::Playing);
+    assert_eq!(outcome.frames, 100);
+}
@@ -0,0 +1,156 @@
+//! Arena quadtree behavior.
+
+use circles::ball::BallState::Normal;
+use circles::ball::{Ball, BallPairIds, WHITE_BALL};
+use circles::geometry::{Point, Rect};
+use circles::quadtree::QuadTree;
+
+const ARENA: Rect = Rect {
+    x: 0.0,
+    y: 0.0,
+    w: 640.0,
+    h: 480.0,
+};
+
+fn ball(id: usize, x: f64, y: f64) -> Ball {
+    Ball::new(
+        id,
+        Point { x, y },
+        Point { x: 0.0, y: 0.0 },
+        WHITE_BALL,
+        Normal,
+    )
+}
+
+fn tree_with(balls: &[Ball]) -> QuadTree {
+    let mut tree = QuadTree::new(ARENA);
+    for ball in balls {
+        tree.insert_ball(ball);
+    }
+    tree
+}
+
+fn candidates(tree: &QuadTree, ids: &[usize]) -> Vec<BallPairIds> {
+    let mut pairs: Vec<BallPairIds> = ids.iter().flat_map(|id| tree.search(*id)).collect();
+    pairs.dedup();
+    pairs
+}
+
+#[test]
+fn search_pairs_overlapping_balls() {
+    let tree = tree_with(&[
+        ball(1, 50.0, 50.0),
+        ball(2, 60.0, 55.0),
+        ball(3, 600.0, 400.0),
+    ]);
+
+    assert!(tree.contains(1) && tree.contains(3));
+    assert!(!tree.contains(4));
+    assert_eq!(
+        candidates(&tree, &[1, 2, 3]),
+        vec![BallPairIds {
+            first: 1,
+            second: 2
+        }]
+    );
+    assert!(tree.search(3).is_empty());
+    assert!(tree.search(4).is_empty());
+}
+
+#[test]
+fn ball_on_a_cell_border_stays_in_the_parent() {
+    // straddles the vertical center line, so it pairs with everything in the root
+    let tree = tree_with(&[
+        ball(1, 320.0, 100.0),
+        ball(2, 50.0, 50.0),
+        ball(3, 600.0, 400.0),
+    ]);
+
+    assert_eq!(tree.search(1).len(), 2);
+    assert_eq!(tree.root().balls.len(), 3);
+}
+
+#[test]
+fn rectangles_mark_leaves_with_many_balls() {
+    let tree = tree_with(&[ball(1, 50.0, 50.0), ball(2, 60.0, 55.0)]);
+    let rects = tree.get_rectangles();
+
+    assert_eq!(rects.len(), tree.len());
+    assert_eq!(rects.iter().filter(|r| r.many).count(), 1);
+    assert_eq!(rects.last().unwrap().rect.w, ARENA.w);
+}
+
+#[test]
+fn clear_reuses_nodes() {
+    let balls = [
+        ball(1, 50.0, 50.0),
+        ball(2, 60.0, 55.0),
+        ball(3, 600.0, 400.0),
+    ];
+    let mut tree = tree_with(&balls);
+    let len = tree.len();
+
+    tree.clear(ARENA);
+    assert!(tree.is_empty());
+    assert_eq!(tree.len(), 1);
+    assert!(tree.search(1).is_empty());
+
+    for ball in &balls {
+        tree.insert_ball(ball);
+    }
+    assert_eq!(tree.len(), len);
+    assert_eq!(
+        candidates(&tree, &[1, 2, 3]),
+        vec![BallPairIds {
+            first: 1,
+            second: 2
+        }]
+    );
+}
+
+fn rebuilt(tree: &QuadTree, balls: &[Ball]) -> QuadTree {
+    let mut fresh = tree.clone();
+    fresh.clear(ARENA);
+    for ball in balls {
+        fresh.insert_ball(ball);
+    }
+    fresh
+}
+
+#[test]
+fn moved_ball_ends_up_where_a_rebuild_puts_it() {
+    let mut balls = vec![
+        ball(1, 50.0, 50.0),
+        ball(2, 60.0, 55.0),
+        ball(3, 600.0, 400.0),
+        ball(4, 330.0, 250.0),
+    ];
+    let mut tree = tree_with(&balls);
+
+    // across the center, into another quadrant, and a ball growing out of its cell
+    for (index, x, y, radius) in [
+        (0, 400.0, 100.0, 15.0),
+        (2, 30.0, 450.0, 15.0),
+        (3, 330.0, 250.0, 60.0),
+        (1, 62.0, 57.0, 15.0),
+    ] {
+        balls[index].pos = Point { x, y };
+        balls[index].next_position = Point { x, y };
+        balls[index].next_radius = radius;
+        tree.move_ball(&balls[index]);
+
+        assert_eq!(tree.layout(), rebuilt(&tree, &balls).layout());
+    }
+}
+
+#[test]
+fn removed_ball_leaves_no_empty_nodes() {
+    let balls = [ball(1, 50.0, 50.0), ball(2, 600.0, 400.0)];
+    let mut tree = tree_with(&balls);
+
+    assert!(tree.remove_ball(2));
+    assert!(!tree.remove_ball(2));
+    assert!(!tree.contains(2));
+    assert_eq!(tree.layout(), rebuilt(&tree, &balls[..1]).layout());
+    assert_eq!(tree.len(), rebuilt(&tree, &balls[..1]).len());
+}
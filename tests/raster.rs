@@ -0,0 +1,168 @@
+//! CPU rasterizer and PNG export.
+//!
+//! `golden/*.png` are reference frames. After an intended change to how frames look,
+//! rerun with `UPDATE_GOLDEN=1` to write the new ones and check them by eye.
+
+use circles::ball::{Color, BLACK, RED, WHITE};
+use circles::constants::{HEIGHT, WIDTH};
+use circles::draw::{Draw, Stroke};
+use circles::geometry::{Point, Rect};
+use circles::logic::GameState;
+use circles::raster::Raster;
+use circles::rendering::{draw_ball, write_text, Renderer};
+use std::fs;
+use std::path::Path;
+
+fn small() -> Raster {
+    Raster::new(Rect {
+        x: 0.0,
+        y: 0.0,
+        w: 40.0,
+        h: 40.0,
+    })
+}
+
+// what `color` drawn with `alpha` over white looks like
+fn over_white(color: Color, alpha: f64) -> Color {
+    let mix = |c: u8| (c as f64 * alpha + 255.0 * (1.0 - alpha)).round() as u8;
+    Color {
+        r: mix(color.r),
+        g: mix(color.g),
+        b: mix(color.b),
+    }
+}
+
+#[test]
+fn balls_are_drawn_with_their_alpha() {
+    let mut game = GameState::with_seed(WIDTH, HEIGHT, 1);
+    game.start_level(1);
+    let ball = game.objects.values().next().unwrap();
+
+    let mut raster = Raster::new(game.rect);
+    draw_ball(&mut raster, ball);
+
+    let center = raster.pixel(ball.pos.x as usize, ball.pos.y as usize);
+    assert_eq!(center, over_white(ball.color, 0.8));
+    let outside = (ball.pos.x + ball.radius + 3.0) as usize;
+    assert_eq!(raster.pixel(outside, ball.pos.y as usize), WHITE);
+}
+
+#[test]
+fn circle_edges_are_anti_aliased() {
+    let mut raster = small();
+    // the edge runs through the middle of pixel column 29
+    raster.circle(Point { x: 20.0, y: 20.5 }, 9.5, Some(BLACK), None);
+
+    assert_eq!(raster.pixel(28, 20), BLACK);
+    assert_eq!(raster.pixel(30, 20), WHITE);
+    let edge = raster.pixel(29, 20);
+    assert!(edge.r > 100 && edge.r < 160, "{:?}", edge);
+}
+
+#[test]
+fn strokes_follow_the_radius() {
+    let mut raster = small();
+    let stroke = Stroke {
+        color: RED,
+        width: 2.0,
+    };
+    raster.circle(Point { x: 20.0, y: 20.0 }, 10.0, None, Some(stroke));
+
+    assert_eq!(raster.pixel(20, 20), WHITE);
+    assert_eq!(raster.pixel(29, 19), RED);
+    assert_eq!(raster.pixel(34, 19), WHITE);
+}
+
+#[test]
+fn rectangles_are_outlines() {
+    let mut raster = small();
+    let rect = Rect {
+        x: 5.0,
+        y: 5.0,
+        w: 20.0,
+        h: 10.0,
+    };
+    raster.rect(rect, RED);
+
+    assert_eq!(raster.pixel(5, 5), RED);
+    assert_eq!(raster.pixel(24, 14), RED);
+    assert_eq!(raster.pixel(15, 10), WHITE);
+    assert_eq!(raster.pixel(25, 10), WHITE);
+}
+
+#[test]
+fn text_sits_on_the_baseline() {
+    let mut raster = small();
+    write_text(&mut raster, 2.0, 20.0, "I");
+
+    let inked = |row: usize| (0..40).any(|col| raster.pixel(col, row) != WHITE);
+    assert!(!inked(5));
+    assert!(inked(6));
+    assert!(inked(19));
+    assert!(!inked(20));
+}
+
+#[test]
+fn clear_paints_white() {
+    let mut raster = small();
+    raster.alpha(0.5);
+    raster.circle(Point { x: 20.0, y: 20.0 }, 15.0, Some(BLACK), None);
+    raster.clear(Rect {
+        x: 0.0,
+        y: 0.0,
+        w: 40.0,
+        h: 40.0,
+    });
+    assert_eq!(raster.pixel(20, 20), WHITE);
+}
+
+#[test]
+fn png_has_the_frame_size() {
+    let mut game = GameState::with_seed(WIDTH, HEIGHT, 2);
+    game.start_level(2);
+    let png = Renderer::new(&game).to_png(true).unwrap();
+
+    let decoder = png::Decoder::new(png.as_slice());
+    let reader = decoder.read_info().unwrap();
+    let info = reader.info();
+    assert_eq!((info.width, info.height), (WIDTH as u32, HEIGHT as u32));
+    assert_eq!(info.color_type, png::ColorType::Rgb);
+}
+
+fn check_golden(name: &str, raster: &Raster) {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden")
+        .join(name);
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        fs::write(&path, raster.to_png().unwrap()).unwrap();
+        return;
+    }
+
+    let file = fs::read(&path).unwrap_or_else(|err| panic!("{}: {}", path.display(), err));
+    let mut reader = png::Decoder::new(file.as_slice()).read_info().unwrap();
+    let mut expected = vec![0; reader.output_buffer_size()];
+    reader.next_frame(&mut expected).unwrap();
+    assert!(
+        raster.rgb() == expected,
+        "{} doesn't match, rerun with UPDATE_GOLDEN=1 if the change is intended",
+        name
+    );
+}
+
+#[test]
+fn golden_frames() {
+    let mut game = GameState::with_seed(WIDTH, HEIGHT, 11);
+    game.start_level(4);
+    for _ in 0..120 {
+        game.tick();
+    }
+    let renderer = Renderer::new(&game);
+
+    let mut frame = Raster::new(game.rect);
+    renderer.render_frame(&mut frame, true);
+    check_golden("level-4-tick-120.png", &frame);
+
+    let mut won = Raster::new(game.rect);
+    renderer.render_won(&mut won);
+    check_golden("won.png", &won);
+}
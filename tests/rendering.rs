@@ -0,0 +1,158 @@
+//! Renderer output checked through the recording draw backend.
+
+use circles::ball::{BLACK, RED};
+use circles::constants::{HEIGHT, WIDTH};
+use circles::draw::{DrawCall, Recording};
+use circles::geometry::Point;
+use circles::logic::GameState;
+use circles::rendering::Renderer;
+
+fn game() -> GameState {
+    let mut game = GameState::with_seed(WIDTH, HEIGHT, 7);
+    game.start_level(1);
+    game
+}
+
+#[test]
+fn state_draws_every_ball_in_id_order() {
+    let game = game();
+    let mut recording = Recording::new();
+    Renderer::new(&game).render_state(&mut recording, true);
+
+    assert_eq!(recording.calls[0], DrawCall::Clear(game.rect));
+
+    let mut balls: Vec<_> = game.objects.values().collect();
+    balls.sort_by_key(|ball| ball.id);
+    let circles: Vec<_> = recording
+        .calls
+        .iter()
+        .filter_map(|call| match call {
+            DrawCall::Circle {
+                center,
+                radius,
+                fill,
+                stroke,
+            } => Some((*center, *radius, *fill, stroke.map(|s| s.color))),
+            _ => None,
+        })
+        .collect();
+    let expected: Vec<_> = balls
+        .iter()
+        .map(|ball| (ball.pos, ball.radius, Some(ball.color), Some(BLACK)))
+        .collect();
+    assert_eq!(circles, expected);
+}
+
+#[test]
+fn state_without_clear_only_draws_balls() {
+    let game = game();
+    let mut recording = Recording::new();
+    Renderer::new(&game).render_state(&mut recording, false);
+
+    assert!(!recording
+        .calls
+        .iter()
+        .any(|call| matches!(call, DrawCall::Clear(_))));
+}
+
+#[test]
+fn won_and_lost_screens() {
+    let game = game();
+    let renderer = Renderer::new(&game);
+
+    let mut won = Recording::new();
+    renderer.render_won(&mut won);
+    assert_eq!(won.calls[0], DrawCall::Clear(game.rect));
+    assert_eq!(
+        won.texts(),
+        vec![
+            "Congratz! You won.",
+            "Click canvas or press 'n' to go to the next level"
+        ]
+    );
+
+    let mut lost = Recording::new();
+    renderer.render_lost(&mut lost);
+    assert_eq!(lost.texts(), vec!["you lost"]);
+
+    let mut last = game.clone();
+    last.start_level(last.all_levels.len());
+    let mut won = Recording::new();
+    Renderer::new(&last).render_won(&mut won);
+    assert_eq!(
+        won.texts()[1],
+        "Click canvas or press 'n' to start over from level 1"
+    );
+}
+
+#[test]
+fn quad_tree_outlines_every_node() {
+    let mut game = game();
+    for _ in 0..5 {
+        game.tick();
+    }
+    let mut recording = Recording::new();
+    Renderer::new(&game).render_quad_tree(&mut recording, false);
+
+    let expected: Vec<_> = game
+        .get_rectangles()
+        .into_iter()
+        .map(|r| DrawCall::Rect(r.rect, if r.many { RED } else { BLACK }))
+        .collect();
+    let drawn: Vec<_> = recording
+        .calls
+        .into_iter()
+        .filter(|call| matches!(call, DrawCall::Rect(..)))
+        .collect();
+    assert!(!drawn.is_empty());
+    assert_eq!(drawn, expected);
+}
+
+#[test]
+fn replay_copies_the_calls() {
+    let game = game();
+    let mut recording = Recording::new();
+    Renderer::new(&game).render_state(&mut recording, true);
+
+    let mut copy = Recording::new();
+    recording.replay(&mut copy);
+    assert_eq!(copy.calls, recording.calls);
+}
+
+#[test]
+fn preview_rings_follow_interpolated_balls() {
+    let mut game = game();
+    for _ in 0..5 {
+        game.advance();
+    }
+    let ids: Vec<usize> = game.objects.keys().copied().collect();
+    let mut recording = Recording::new();
+    Renderer::interpolated(&game, 0.5).render_preview(
+        &mut recording,
+        Point { x: 10.0, y: 10.0 },
+        &ids,
+    );
+
+    let rings: Vec<_> = recording
+        .calls
+        .iter()
+        .filter_map(|call| match call {
+            DrawCall::Circle {
+                center,
+                radius,
+                fill: None,
+                ..
+            } => Some((*center, *radius)),
+            _ => None,
+        })
+        .collect();
+    for ball in game.objects.values() {
+        let drawn = ball.interpolated(0.5);
+        assert_ne!(drawn.pos, ball.pos);
+        assert!(
+            rings.contains(&(drawn.pos, drawn.radius + 3.0)),
+            "no ring around ball {}",
+            ball.id
+        );
+    }
+}
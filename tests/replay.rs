@@ -0,0 +1,160 @@
+//! Recording a game and playing it back natively.
+
+use circles::constants::{HEIGHT, WIDTH};
+use circles::levels::{LevelError, LevelPack};
+use circles::logic::{ChangeState, GameResult, GameState};
+use circles::replay::{Input, Playback, Replay, ReplayError};
+
+fn record_game(seed: u64) -> GameState {
+    let mut game = GameState::with_seed(WIDTH, HEIGHT, seed);
+
+    for _ in 0..30 {
+        game.advance();
+    }
+    game.apply_input(Input::Change(ChangeState::PlayPause));
+    game.advance();
+    game.apply_input(Input::Change(ChangeState::PlayPause));
+    game.apply_input(Input::Click {
+        x: 321.5,
+        y: 200.25,
+    });
+
+    while game.result == GameResult::Playing {
+        game.advance();
+    }
+    game.apply_input(Input::Change(ChangeState::RestartLevel));
+    for _ in 0..10 {
+        game.advance();
+    }
+    game.apply_input(Input::Click { x: 100.0, y: 400.0 });
+    for _ in 0..500 {
+        game.advance();
+    }
+    game
+}
+
+#[test]
+fn playback_reproduces_outcome() {
+    let recorded = record_game(1234);
+
+    let mut playback = Playback::new(recorded.replay.clone());
+    let mut game = playback.new_game();
+    while !playback.is_finished() {
+        playback.step(&mut game);
+    }
+    while game.frame_id < recorded.frame_id && game.result == GameResult::Playing {
+        game.advance();
+    }
+
+    assert_eq!(game.replay, recorded.replay);
+    assert_eq!(game.level_id, recorded.level_id);
+    assert_eq!(game.frame_id, recorded.frame_id);
+    assert_eq!(game.captured, recorded.captured);
+    assert_eq!(game.shots, recorded.shots);
+    assert_eq!(game.result, recorded.result);
+    assert_eq!(game.objects, recorded.objects);
+}
+
+#[test]
+fn text_format_round_trips() {
+    let recorded = record_game(99);
+    let text = recorded.replay.to_string();
+
+    assert!(text.starts_with("circles-replay 3\nseed 99\narena 640 480\nlevel 1 5 2\n"));
+    assert_eq!(text.parse::<Replay>(), Ok(recorded.replay));
+}
+
+#[test]
+fn malformed_replays_are_rejected() {
+    assert_eq!("seed 1".parse::<Replay>(), Err(ReplayError::MissingHeader));
+    assert_eq!(
+        "circles-replay 3\n".parse::<Replay>(),
+        Err(ReplayError::MissingSeed)
+    );
+    assert_eq!(
+        "circles-replay 3\nseed 1\n".parse::<Replay>(),
+        Err(ReplayError::MissingArena)
+    );
+    assert_eq!(
+        "circles-replay 3\nseed 1\narena 640 480\n4 click 1 2".parse::<Replay>(),
+        Err(ReplayError::MissingLevels)
+    );
+    assert_eq!(
+        "circles-replay 3\nseed 1\narena 640 480\nlevel 0 5 1\n".parse::<Replay>(),
+        Err(ReplayError::InvalidLevel(4, LevelError::NoShots))
+    );
+    assert_eq!(
+        "circles-replay 3\nseed 1\narena 640 480\nlevel 1 5 1 PINK_BALL:1\n".parse::<Replay>(),
+        Err(ReplayError::InvalidLevel(
+            4,
+            LevelError::UnknownBallType("PINK_BALL".to_string())
+        ))
+    );
+    assert_eq!(
+        "circles-replay 3\nseed 1\narena 640 480\nlevel 1 5 1\n5 jump".parse::<Replay>(),
+        Err(ReplayError::InvalidLine(5, "5 jump".to_string()))
+    );
+    assert_eq!(
+        "circles-replay 3\nseed 1\narena 640 480\nlevel 1 5 1\n5 click x 4".parse::<Replay>(),
+        Err(ReplayError::InvalidNumber(5, "x".to_string()))
+    );
+}
+
+#[test]
+fn old_versions_are_rejected() {
+    // version 1 spawned balls differently, the same seed gives another layout
+    let old = "circles-replay 1\nseed 5\n12 click 320 240\n";
+    assert_eq!(
+        old.parse::<Replay>(),
+        Err(ReplayError::UnsupportedVersion("1".to_string()))
+    );
+    // version 2 didn't record the levels and always played the classic ones
+    assert_eq!(
+        "circles-replay 2\nseed 5\narena 640 480\n".parse::<Replay>(),
+        Err(ReplayError::UnsupportedVersion("2".to_string()))
+    );
+    assert_eq!(
+        "circles-replay 9\nseed 5\narena 640 480\n".parse::<Replay>(),
+        Err(ReplayError::UnsupportedVersion("9".to_string()))
+    );
+}
+
+#[test]
+fn level_pack_is_recorded() {
+    let pack = LevelPack::from_json(
+        r#"{
+            "name": "custom",
+            "arena": { "width": 400, "height": 300 },
+            "levels": [
+                { "shots": 2, "balls": 12, "capture": 3,
+                  "ball_types": [{ "type": "RED_BALL", "weight": 3 }, { "type": "SILVER_BALL", "weight": 0.5 }] },
+                { "shots": 1, "balls": 40, "capture": 30 }
+            ]
+        }"#,
+    )
+    .unwrap();
+    let mut game = GameState::from_level_pack(&pack, 8);
+    game.apply_input(Input::Click { x: 200.0, y: 150.0 });
+    for _ in 0..200 {
+        game.advance();
+    }
+
+    let text = game.replay.to_string();
+    assert!(
+        text.contains("level 2 12 3 RED_BALL:3 SILVER_BALL:0.5\nlevel 1 40 30\n"),
+        "{}",
+        text
+    );
+    let replay: Replay = text.parse().unwrap();
+    assert_eq!(replay.levels, pack.levels);
+
+    // the pack's levels, not the classic ones
+    let mut playback = Playback::new(replay);
+    let mut replayed = playback.new_game();
+    assert_eq!(replayed.all_levels, pack.levels);
+    while replayed.frame_id < game.frame_id {
+        playback.step(&mut replayed);
+    }
+    assert_eq!(replayed.objects, game.objects);
+    assert_eq!(replayed.captured, game.captured);
+}
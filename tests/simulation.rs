@@ -0,0 +1,104 @@
+//! Native tests for the simulation core, no browser needed.
+
+use circles::constants::{HEIGHT, WIDTH};
+use circles::logic::{GameResult, GameState};
+use circles::quadtree::QuadTree;
+
+#[test]
+fn new_game_spawns_first_level() {
+    let game = GameState::new(WIDTH, HEIGHT);
+
+    assert_eq!(game.level_id, 1);
+    assert_eq!(game.objects.len(), game.level().num_of_balls);
+    assert_eq!(game.result, GameResult::Playing);
+}
+
+#[test]
+fn balls_stay_inside_arena() {
+    let mut game = GameState::new(WIDTH, HEIGHT);
+
+    for _ in 0..1000 {
+        game.tick();
+    }
+
+    assert_eq!(game.frame_id, 1000);
+    for ball in game.objects.values() {
+        assert!(ball.pos.x >= -ball.radius && ball.pos.x <= WIDTH as f64 + ball.radius);
+        assert!(ball.pos.y >= -ball.radius && ball.pos.y <= HEIGHT as f64 + ball.radius);
+    }
+}
+
+#[test]
+fn capture_ball_uses_a_shot_and_level_ends() {
+    let mut game = GameState::new(WIDTH, HEIGHT);
+    let shots = game.shots;
+
+    game.create_capture_ball(WIDTH as f64 / 2.0, HEIGHT as f64 / 2.0);
+    assert_eq!(game.shots, shots - 1);
+
+    while game.result == GameResult::Playing {
+        game.tick();
+        assert!(game.frame_id < 10_000, "active ball never vanished");
+    }
+    assert!(game.captured >= 1);
+}
+
+fn layout(game: &GameState) -> Vec<(usize, f64, f64, f64, f64, f64)> {
+    let mut balls: Vec<_> = game
+        .objects
+        .values()
+        .map(|b| (b.id, b.pos.x, b.pos.y, b.velocity.x, b.velocity.y, b.radius))
+        .collect();
+    balls.sort_by_key(|b| b.0);
+    balls
+}
+
+#[test]
+fn same_seed_gives_same_layout() {
+    let first = GameState::with_seed(WIDTH, HEIGHT, 42);
+    let second = GameState::with_seed(WIDTH, HEIGHT, 42);
+    let other = GameState::with_seed(WIDTH, HEIGHT, 43);
+
+    assert_eq!(layout(&first), layout(&second));
+    assert_ne!(layout(&first), layout(&other));
+}
+
+#[test]
+fn layout_depends_only_on_seed_and_level() {
+    let mut played = GameState::with_seed(WIDTH, HEIGHT, 7);
+    for _ in 0..100 {
+        played.tick();
+    }
+    played.next_level();
+
+    let mut fresh = GameState::with_seed(WIDTH, HEIGHT, 7);
+    fresh.next_level();
+    assert_eq!(fresh.level_id, 2);
+    assert_eq!(layout(&played), layout(&fresh));
+
+    let before = layout(&played);
+    for _ in 0..100 {
+        played.tick();
+    }
+    played.restart();
+    assert_eq!(layout(&played), before);
+}
+
+#[test]
+fn incremental_quadtree_matches_rebuilt_tree() {
+    for seed in 0..5 {
+        let mut game = GameState::with_seed(WIDTH, HEIGHT, seed);
+        let mut tree = QuadTree::new(game.rect);
+        tree.check_incremental = true;
+        game.broad_phase = Box::new(tree);
+        game.next_level();
+        game.next_level();
+
+        for frame in 0..600 {
+            if frame == 100 {
+                game.create_capture_ball(WIDTH as f64 / 3.0, HEIGHT as f64 / 2.0);
+            }
+            game.tick();
+        }
+    }
+}
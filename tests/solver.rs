@@ -0,0 +1,134 @@
+//! Searching for the best shot by simulating ahead.
+
+use circles::ball::BallState::{Expanding, Shrinking};
+use circles::broadphase::BroadPhaseKind;
+use circles::constants::{HEIGHT, WIDTH};
+use circles::geometry::Point;
+use circles::logic::{ChangeState, GameResult, GameState};
+use circles::player::{play_level, GreedyBot};
+use circles::replay::Input;
+use circles::solver::{captured_by_click, solve, SolverBot, SolverConfig};
+
+const QUICK: SolverConfig = SolverConfig {
+    max_delay: 20,
+    delay_step: 10,
+    grid_step: 80.0,
+    horizon: 3_000,
+};
+
+fn level(seed: u64, level_id: usize) -> GameState {
+    let mut game = GameState::with_seed(WIDTH, HEIGHT, seed);
+    game.start_level(level_id);
+    game
+}
+
+// plays the shot on the real game and counts what it captured
+fn shoot(game: &mut GameState, delay: usize, point: Point) -> usize {
+    for _ in 0..delay {
+        game.advance();
+    }
+    let before = game.captured;
+    game.apply_input(Input::Click {
+        x: point.x,
+        y: point.y,
+    });
+    loop {
+        game.advance();
+        let capturing = game
+            .objects
+            .values()
+            .any(|ball| ball.ball_state == Expanding || ball.ball_state == Shrinking);
+        if !capturing {
+            return game.captured - before - 1;
+        }
+    }
+}
+
+#[test]
+fn predicted_captures_happen() {
+    for seed in 0..3 {
+        let mut game = level(seed, 3);
+        let shot = solve(&game, &QUICK).unwrap();
+        assert!(shot.delay <= QUICK.max_delay);
+        assert_eq!(shoot(&mut game, shot.delay, shot.point), shot.captured);
+    }
+}
+
+#[test]
+fn preview_lists_the_captured_balls() {
+    let game = level(6, 5);
+    let shot = solve(&game, &SolverConfig::hint()).unwrap();
+    let captured = captured_by_click(&game, shot.point, 3_000);
+    assert_eq!(captured.len(), shot.captured);
+    assert!(captured.windows(2).all(|pair| pair[0] < pair[1]));
+
+    // the same balls get captured in the real game
+    let mut played = game.clone();
+    played.apply_input(Input::Click {
+        x: shot.point.x,
+        y: shot.point.y,
+    });
+    for _ in 0..3_000 {
+        played.tick();
+    }
+    for id in game.objects.keys() {
+        let was_captured = played.objects.get(id).is_none_or(|ball| ball.is_captured);
+        assert_eq!(was_captured, captured.contains(id), "ball {}", id);
+    }
+}
+
+#[test]
+fn sandbox_leaves_the_recording_behind() {
+    let mut game = level(2, 2);
+    game.apply_input(Input::Change(ChangeState::PlayPause));
+    game.apply_input(Input::Change(ChangeState::PlayPause));
+    assert!(!game.replay.events.is_empty());
+
+    let sandbox = game.sandbox(BroadPhaseKind::SweepAndPrune);
+    assert!(sandbox.replay.events.is_empty());
+    assert_eq!(sandbox.objects, game.objects);
+    assert_eq!(
+        (sandbox.level_id, sandbox.frame_id, sandbox.shots),
+        (game.level_id, game.frame_id, game.shots)
+    );
+}
+
+#[test]
+fn at_least_as_good_as_greedy() {
+    for seed in 0..3 {
+        let game = level(seed, 4);
+        let (point, _) = GreedyBot::best_click(&game).unwrap();
+        let greedy = shoot(&mut game.clone(), 0, point);
+
+        let shot = solve(&game, &QUICK).unwrap();
+        assert!(shot.captured >= greedy, "{} < {}", shot.captured, greedy);
+    }
+}
+
+#[test]
+fn leaves_the_game_alone() {
+    let game = level(1, 2);
+    let frame_id = game.frame_id;
+    let replay = game.replay.clone();
+
+    solve(&game, &QUICK);
+    assert_eq!(game.frame_id, frame_id);
+    assert_eq!(game.replay, replay);
+}
+
+#[test]
+fn no_shot_without_shots() {
+    let mut game = level(1, 1);
+    game.create_capture_ball(100.0, 100.0);
+    assert_eq!(game.shots, 0);
+    assert_eq!(solve(&game, &QUICK), None);
+}
+
+#[test]
+fn solver_bot_beats_early_levels() {
+    for seed in 0..3 {
+        let mut game = level(seed, 1);
+        let outcome = play_level(&mut SolverBot::new(QUICK), &mut game, 20_000);
+        assert_eq!(outcome.result, GameResult::Won);
+    }
+}
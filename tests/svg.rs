@@ -0,0 +1,68 @@
+//! SVG export of a frame.
+
+use circles::ball::{BLACK, RED};
+use circles::broadphase::BroadPhaseKind;
+use circles::constants::{HEIGHT, WIDTH};
+use circles::draw::Draw;
+use circles::geometry::{Point, Rect};
+use circles::logic::GameState;
+use circles::rendering::Renderer;
+use circles::svg::Svg;
+
+fn game() -> GameState {
+    let mut game = GameState::with_seed(WIDTH, HEIGHT, 5);
+    game.start_level(3);
+    game
+}
+
+#[test]
+fn frame_is_a_standalone_document() {
+    let game = game();
+    let svg = Renderer::new(&game).to_svg(false);
+
+    assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\""));
+    assert!(svg.contains(&format!("viewBox=\"0 0 {} {}\"", WIDTH, HEIGHT)));
+    assert!(svg.trim_end().ends_with("</svg>"));
+    assert_eq!(svg.matches("<circle").count(), game.objects.len());
+    for ball in game.objects.values() {
+        let circle = format!(
+            "<circle cx=\"{}\" cy=\"{}\" r=\"{}\" fill=\"{}\" stroke=\"{}\" stroke-width=\"1\" opacity=\"0.8\"/>",
+            ball.pos.x, ball.pos.y, ball.radius, ball.color, BLACK
+        );
+        assert!(svg.contains(&circle), "missing {}", circle);
+    }
+}
+
+#[test]
+fn quad_tree_cells_are_optional() {
+    let mut game = game();
+    game.use_broad_phase(BroadPhaseKind::QuadTree);
+    for _ in 0..5 {
+        game.tick();
+    }
+    let renderer = Renderer::new(&game);
+
+    let cells = game.get_rectangles().len();
+    assert!(cells > 0);
+    let outlines = |svg: &str| svg.matches("fill=\"none\" stroke=").count();
+    assert_eq!(outlines(&renderer.to_svg(false)), 0);
+    assert_eq!(outlines(&renderer.to_svg(true)), cells);
+}
+
+#[test]
+fn clearing_the_whole_frame_drops_what_was_drawn() {
+    let rect = Rect {
+        x: 0.0,
+        y: 0.0,
+        w: 100.0,
+        h: 100.0,
+    };
+    let mut svg = Svg::new(rect);
+    svg.circle(Point { x: 10.0, y: 10.0 }, 5.0, Some(RED), None);
+    svg.clear(rect);
+    svg.text(5.0, 20.0, "a < b & \"c\"", BLACK);
+
+    let doc = svg.finish();
+    assert!(!doc.contains("<circle"));
+    assert!(doc.contains(">a &lt; b &amp; &quot;c&quot;</text>"));
+}
@@ -0,0 +1,104 @@
+//! Fixed-timestep clock and drawing between ticks.
+
+use circles::constants::{HEIGHT, SLOW, TICKS_PER_SECOND, WIDTH};
+use circles::draw::{DrawCall, Recording};
+use circles::logic::GameState;
+use circles::rendering::Renderer;
+use circles::timestep::FixedStep;
+
+// ticks run for one simulated second of display frames at `hz`
+fn ticks_in_a_second(hz: f64) -> usize {
+    let mut clock = FixedStep::new();
+    let frame = 1000.0 / hz;
+    (0..=hz as usize)
+        .map(|i| clock.advance(1000.0 + i as f64 * frame))
+        .sum()
+}
+
+#[test]
+fn same_ticks_per_second_at_any_refresh_rate() {
+    for hz in [30.0, 60.0, 75.0, 144.0, 240.0] {
+        let ticks = ticks_in_a_second(hz) as f64;
+        assert!(
+            (ticks - TICKS_PER_SECOND).abs() <= 1.0,
+            "{} ticks at {} Hz",
+            ticks,
+            hz
+        );
+    }
+}
+
+#[test]
+fn first_frame_only_starts_the_clock() {
+    let mut clock = FixedStep::new();
+    assert_eq!(clock.advance(5_000.0), 0);
+    assert_eq!(clock.advance(5_050.0), 3);
+}
+
+#[test]
+fn leftover_time_carries_over() {
+    let mut clock = FixedStep::new();
+    clock.advance(0.0);
+    // a 144 Hz frame is less than a tick
+    assert_eq!(clock.advance(1000.0 / 144.0), 0);
+    assert!(clock.alpha() > 0.4 && clock.alpha() < 0.5);
+    assert_eq!(clock.advance(2000.0 / 144.0), 0);
+    assert_eq!(clock.advance(3000.0 / 144.0), 1);
+    assert!(clock.alpha() < 0.3);
+}
+
+#[test]
+fn long_pauses_are_not_caught_up() {
+    let mut clock = FixedStep::new();
+    clock.advance(0.0);
+    assert_eq!(clock.advance(60_000.0), 15);
+    assert_eq!(clock.advance(60_000.0), 0);
+    assert_eq!(clock.advance(59_000.0), 0);
+}
+
+#[test]
+fn balls_are_drawn_between_ticks() {
+    let mut game = GameState::with_seed(WIDTH, HEIGHT, 4);
+    game.start_level(2);
+    game.tick();
+
+    let centers = |interpolation: f64| {
+        let mut recording = Recording::new();
+        Renderer::interpolated(&game, interpolation).render_state(&mut recording, false);
+        recording
+            .calls
+            .into_iter()
+            .filter_map(|call| match call {
+                DrawCall::Circle { center, .. } => Some(center),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let mut balls: Vec<_> = game.objects.values().collect();
+    balls.sort_by_key(|ball| ball.id);
+    let previous: Vec<_> = balls.iter().map(|ball| ball.previous_position).collect();
+    let current: Vec<_> = balls.iter().map(|ball| ball.pos).collect();
+
+    assert_eq!(centers(0.0), previous);
+    assert_eq!(centers(1.0), current);
+    for ((middle, before), after) in centers(0.5).iter().zip(&previous).zip(&current) {
+        assert!((middle.x - (before.x + after.x) / 2.0).abs() < 1e-9);
+        assert!((middle.y - (before.y + after.y) / 2.0).abs() < 1e-9);
+    }
+}
+
+#[test]
+fn speeds_are_per_second() {
+    let mut game = GameState::with_seed(WIDTH, HEIGHT, 6);
+    game.start_level(1);
+    let ball = *game.objects.values().next().unwrap();
+    let id = ball.id;
+
+    // a bounce only changes the velocity for the next tick
+    game.tick();
+    let moved = game.objects[&id].pos;
+    assert_eq!(moved.x, ball.pos.x + ball.velocity.x / TICKS_PER_SECOND);
+    assert_eq!(moved.y, ball.pos.y + ball.velocity.y / TICKS_PER_SECOND);
+    assert!(ball.velocity.x.abs() >= 0.8 * SLOW);
+}
@@ -0,0 +1,78 @@
+//! Mapping between arena units and a scaled, high density canvas.
+
+use circles::geometry::{Point, Rect};
+use circles::viewport::Viewport;
+
+fn arena() -> Rect {
+    Rect {
+        x: 0.0,
+        y: 0.0,
+        w: 640.0,
+        h: 480.0,
+    }
+}
+
+#[test]
+fn fit_keeps_aspect_ratio() {
+    // wide container, the height limits
+    let viewport = Viewport::fit(arena(), 1920.0, 960.0, 1.0);
+    assert_eq!((viewport.css_width, viewport.css_height), (1280.0, 960.0));
+
+    // narrow container, the width limits
+    let viewport = Viewport::fit(arena(), 320.0, 960.0, 1.0);
+    assert_eq!((viewport.css_width, viewport.css_height), (320.0, 240.0));
+
+    // no height, only the width counts
+    let viewport = Viewport::fit(arena(), 960.0, 0.0, 1.0);
+    assert_eq!((viewport.css_width, viewport.css_height), (960.0, 720.0));
+}
+
+#[test]
+fn backing_store_follows_pixel_ratio() {
+    let viewport = Viewport::fit(arena(), 640.0, 480.0, 2.0);
+    assert_eq!((viewport.css_width, viewport.css_height), (640.0, 480.0));
+    assert_eq!(viewport.backing_size(), (1280, 960));
+    assert_eq!(viewport.scale(), (2.0, 2.0));
+
+    let viewport = Viewport::fit(arena(), 320.0, 240.0, 1.5);
+    assert_eq!(viewport.backing_size(), (480, 360));
+    assert_eq!(viewport.scale(), (0.75, 0.75));
+
+    // a broken ratio falls back to one device pixel per CSS pixel
+    let viewport = Viewport::fit(arena(), 640.0, 480.0, 0.0);
+    assert_eq!(viewport.backing_size(), (640, 480));
+}
+
+#[test]
+fn pointer_maps_to_arena_units() {
+    // shown at half size on a retina screen, the pixel ratio doesn't change the mapping
+    let viewport = Viewport::new(arena(), 320.0, 240.0, 2.0);
+    assert_eq!(viewport.to_game(0.0, 0.0), Point { x: 0.0, y: 0.0 });
+    assert_eq!(viewport.to_game(160.0, 120.0), Point { x: 320.0, y: 240.0 });
+    assert_eq!(viewport.to_game(320.0, 240.0), Point { x: 640.0, y: 480.0 });
+
+    // stretched by page styles, each axis scales on its own
+    let viewport = Viewport::new(arena(), 1280.0, 480.0, 1.0);
+    assert_eq!(viewport.to_game(640.0, 240.0), Point { x: 320.0, y: 240.0 });
+}
+
+#[test]
+fn pointer_outside_arena() {
+    let viewport = Viewport::new(arena(), 320.0, 240.0, 1.0);
+    assert!(viewport.contains(viewport.to_game(10.0, 230.0)));
+    assert!(!viewport.contains(viewport.to_game(-1.0, 100.0)));
+    assert!(!viewport.contains(viewport.to_game(100.0, 241.0)));
+}
+
+#[test]
+fn arena_offset_is_kept() {
+    let rect = Rect {
+        x: 100.0,
+        y: 50.0,
+        w: 200.0,
+        h: 100.0,
+    };
+    let viewport = Viewport::fit(rect, 400.0, 400.0, 1.0);
+    assert_eq!(viewport.to_game(0.0, 0.0), Point { x: 100.0, y: 50.0 });
+    assert_eq!(viewport.to_game(400.0, 200.0), Point { x: 300.0, y: 150.0 });
+}